@@ -0,0 +1,116 @@
+use crate::models::ImageEviction;
+use crate::storage::HistoryStore;
+use std::fs;
+use tauri::{AppHandle, Emitter};
+
+/// 统计图片条目占用的磁盘空间，超过上限时从最旧的未固定图片开始删除，直到回到上限以内。
+/// 没有发生清理时返回 None
+pub fn enforce(store: &HistoryStore, quota_bytes: u64) -> Result<Option<ImageEviction>, String> {
+    let entries: Vec<_> = store
+        .image_entries()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|entry| {
+            let size: u64 = entry
+                .paths
+                .iter()
+                .filter_map(|path| fs::metadata(path).ok())
+                .map(|meta| meta.len())
+                .sum();
+            (entry, size)
+        })
+        .collect();
+
+    let mut used: u64 = entries.iter().map(|(_, size)| size).sum();
+    if used <= quota_bytes {
+        return Ok(None);
+    }
+
+    let mut report = ImageEviction {
+        quota_bytes,
+        ..Default::default()
+    };
+    for (entry, size) in entries {
+        if used <= quota_bytes {
+            break;
+        }
+        if entry.pinned {
+            continue;
+        }
+        if let Some(item) = store.delete(entry.id).map_err(|e| e.to_string())? {
+            store.delete_files(item.image_path.as_deref(), item.thumbnail_path.as_deref());
+            used -= size;
+            report.freed_bytes += size;
+            report.evicted.push(entry.id);
+        }
+    }
+    report.used_bytes = used;
+    Ok(Some(report))
+}
+
+/// 执行清理，有图片被删除时发出 images-evicted 事件
+pub fn enforce_and_report(app: &AppHandle, store: &HistoryStore, quota_bytes: u64) {
+    match enforce(store, quota_bytes) {
+        Ok(Some(report)) if !report.evicted.is_empty() => {
+            let _ = app.emit("images-evicted", report);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to enforce image quota: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ClipboardPayload;
+    use std::path::Path;
+
+    fn add_image(store: &HistoryStore, dir: &Path, name: &str, size: usize) -> i64 {
+        let path = dir.join(format!("{name}.png"));
+        fs::write(&path, vec![0u8; size]).unwrap();
+        store
+            .upsert(&ClipboardPayload {
+                content_type: "image".to_string(),
+                content_hash: name.to_string(),
+                image_path: Some(path.to_string_lossy().to_string()),
+                file_size: size as i64,
+                ..Default::default()
+            })
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn evicts_oldest_unpinned_images_until_under_quota() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let pinned = add_image(&store, dir.path(), "pinned", 400);
+        let oldest = add_image(&store, dir.path(), "oldest", 300);
+        let middle = add_image(&store, dir.path(), "middle", 300);
+        let newest = add_image(&store, dir.path(), "newest", 300);
+        store.set_pinned(pinned, true).unwrap();
+
+        assert!(enforce(&store, 2000).unwrap().is_none());
+
+        let report = enforce(&store, 800).unwrap().unwrap();
+        assert_eq!(report.evicted, vec![oldest, middle]);
+        assert_eq!(report.freed_bytes, 600);
+        assert_eq!(report.used_bytes, 700);
+        assert!(!dir.path().join("oldest.png").exists());
+        assert!(store.get(pinned).unwrap().is_some());
+        assert!(store.get(newest).unwrap().is_some());
+    }
+
+    #[test]
+    fn pinned_images_are_never_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let id = add_image(&store, dir.path(), "big", 1000);
+        store.set_pinned(id, true).unwrap();
+
+        let report = enforce(&store, 100).unwrap().unwrap();
+        assert!(report.evicted.is_empty());
+        assert_eq!(report.used_bytes, 1000);
+        assert!(store.get(id).unwrap().unwrap().pinned);
+    }
+}
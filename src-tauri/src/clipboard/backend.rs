@@ -0,0 +1,216 @@
+use crate::models::{ClipboardFormat, SourceApp};
+use arboard::{Clipboard, ImageData};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// 剪贴板监听后端：负责等待变化并读取当前内容
+pub trait ClipboardBackend: Send {
+    /// 阻塞直到剪贴板可能发生了变化，返回错误时调用方应改用轮询后端
+    fn wait_for_change(&mut self) -> Result<(), String>;
+
+    fn read_text(&mut self) -> Option<String>;
+
+    fn read_image(&mut self) -> Option<ImageData<'static>>;
+
+    /// 读取纯文本和图片之外的其他表示形式（HTML、RTF 等）
+    fn read_formats(&mut self) -> Vec<ClipboardFormat>;
+
+    /// 读取文件管理器复制的文件列表
+    fn read_files(&mut self) -> Vec<PathBuf>;
+
+    /// 当前内容提供的全部类型（MIME 类型、X11 目标或平台格式名）
+    fn mime_types(&mut self) -> Vec<String>;
+
+    /// 读取指定类型的原始数据
+    fn read_data(&mut self, mime_type: &str) -> Option<Vec<u8>>;
+
+    /// 单个格式的大小上限，超过时放弃读取。默认由读取方自行丢弃
+    fn set_max_size(&mut self, _max_bytes: i64) {}
+
+    /// 当前剪贴板内容来自哪个应用，默认取前台应用
+    fn source_app(&mut self) -> Option<SourceApp> {
+        crate::focus::frontmost_app()
+    }
+}
+
+/// 是否需要作为额外格式保存：纯文本、图片和文件列表已单独保存，X11 的
+/// TARGETS/TIMESTAMP 等非 MIME 目标也没有意义
+pub fn is_extra_format(mime_type: &str) -> bool {
+    mime_type.contains('/')
+        && !mime_type.starts_with("text/plain")
+        && !mime_type.starts_with("image/")
+        && mime_type != "text/uri-list"
+        && mime_type != "x-special/gnome-copied-files"
+}
+
+/// 轮询后端，每 300ms 读取一次剪贴板，作为其他后端不可用时的兜底
+pub struct PollingBackend {
+    clipboard: Clipboard,
+    first: bool,
+}
+
+impl PollingBackend {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            clipboard: Clipboard::new().map_err(|e| e.to_string())?,
+            first: true,
+        })
+    }
+}
+
+impl ClipboardBackend for PollingBackend {
+    fn wait_for_change(&mut self) -> Result<(), String> {
+        // 启动时立即读取一次已有内容
+        if !std::mem::take(&mut self.first) {
+            thread::sleep(Duration::from_millis(300));
+        }
+        Ok(())
+    }
+
+    fn read_text(&mut self) -> Option<String> {
+        self.clipboard.get_text().ok()
+    }
+
+    fn read_image(&mut self) -> Option<ImageData<'static>> {
+        self.clipboard.get_image().ok()
+    }
+
+    fn read_formats(&mut self) -> Vec<ClipboardFormat> {
+        // arboard 只能跨平台读取 HTML
+        match self.clipboard.get().html() {
+            Ok(html) => vec![ClipboardFormat {
+                mime_type: "text/html".to_string(),
+                data: html.into_bytes(),
+            }],
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn read_files(&mut self) -> Vec<PathBuf> {
+        self.clipboard.get().file_list().unwrap_or_default()
+    }
+
+    fn mime_types(&mut self) -> Vec<String> {
+        super::concealed::platform_types()
+    }
+
+    fn read_data(&mut self, _mime_type: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// 选择当前平台可用的最佳后端，可通过 SUPER_PASTE_CLIPBOARD_BACKEND 强制指定
+/// (`wayland` / `x11` / `polling`)，便于在无头合成器下测试
+pub fn select_backend() -> Result<Box<dyn ClipboardBackend>, String> {
+    let forced = std::env::var("SUPER_PASTE_CLIPBOARD_BACKEND").ok();
+
+    #[cfg(target_os = "linux")]
+    {
+        let wants = |name: &str| forced.as_deref().is_none_or(|f| f == name);
+
+        // Wayland 会话下 XFixes 只能看到 XWayland 客户端，优先使用 data-control
+        if wants("wayland") && std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            match super::wayland::WaylandBackend::new() {
+                Ok(backend) => return Ok(Box::new(backend)),
+                Err(e) => eprintln!("Wayland data-control watcher unavailable: {}", e),
+            }
+        }
+
+        if wants("x11") {
+            match super::x11::X11Backend::new() {
+                Ok(backend) => return Ok(Box::new(backend)),
+                Err(e) => eprintln!("XFixes clipboard watcher unavailable: {}", e),
+            }
+        }
+    }
+
+    if let Some(forced) = forced.as_deref().filter(|f| *f != "polling") {
+        eprintln!("Clipboard backend {} unavailable, falling back to polling", forced);
+    }
+    Ok(Box::new(PollingBackend::new()?))
+}
+
+/// 内存后端，测试时直接设置剪贴板内容，无需桌面会话
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryBackend {
+    text: Option<String>,
+    image: Option<ImageData<'static>>,
+    formats: Vec<ClipboardFormat>,
+    files: Vec<PathBuf>,
+    source_app: Option<SourceApp>,
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    pub fn set_text(&mut self, text: &str) {
+        self.text = Some(text.to_string());
+    }
+
+    pub fn set_image(&mut self, width: usize, height: usize, bytes: Vec<u8>) {
+        self.image = Some(ImageData {
+            width,
+            height,
+            bytes: bytes.into(),
+        });
+    }
+
+    pub fn set_format(&mut self, mime_type: &str, data: &[u8]) {
+        self.formats.retain(|f| f.mime_type != mime_type);
+        self.formats.push(ClipboardFormat {
+            mime_type: mime_type.to_string(),
+            data: data.to_vec(),
+        });
+    }
+
+    pub fn set_files(&mut self, files: &[PathBuf]) {
+        self.files = files.to_vec();
+    }
+
+    pub fn set_source_app(&mut self, app: SourceApp) {
+        self.source_app = Some(app);
+    }
+}
+
+#[cfg(test)]
+impl ClipboardBackend for MemoryBackend {
+    fn wait_for_change(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn read_text(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn read_image(&mut self) -> Option<ImageData<'static>> {
+        self.image.clone()
+    }
+
+    fn read_formats(&mut self) -> Vec<ClipboardFormat> {
+        self.formats
+            .iter()
+            .filter(|f| is_extra_format(&f.mime_type))
+            .cloned()
+            .collect()
+    }
+
+    fn read_files(&mut self) -> Vec<PathBuf> {
+        self.files.clone()
+    }
+
+    fn mime_types(&mut self) -> Vec<String> {
+        self.formats.iter().map(|f| f.mime_type.clone()).collect()
+    }
+
+    fn read_data(&mut self, mime_type: &str) -> Option<Vec<u8>> {
+        self.formats
+            .iter()
+            .find(|f| f.mime_type == mime_type)
+            .map(|f| f.data.clone())
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        self.source_app.clone()
+    }
+}
@@ -0,0 +1,128 @@
+use super::backend::ClipboardBackend;
+
+/// KeePassXC 等程序写入的 KDE 提示，值为 secret 时表示密码
+const KDE_PASSWORD_HINT: &str = "x-kde-passwordManagerHint";
+
+/// 部分 Linux 剪贴板管理器约定的状态标记，值为 sensitive 时不记录
+const CLIPBOARD_STATE: &str = "CLIPBOARD_STATE";
+
+/// 只要出现就表示内容不应被记录的类型（Windows / macOS 约定）
+const CONCEALED_TYPES: &[&str] = &[
+    "ExcludeClipboardContentFromMonitorProcessing",
+    "org.nspasteboard.ConcealedType",
+    "org.nspasteboard.TransientType",
+];
+
+/// 检查当前内容是否被来源程序标记为敏感或临时，返回命中的标记
+pub fn detect(backend: &mut dyn ClipboardBackend) -> Option<String> {
+    let mime_types = backend.mime_types();
+    if let Some(mime_type) = mime_types
+        .iter()
+        .find(|m| CONCEALED_TYPES.contains(&m.as_str()))
+    {
+        return Some(mime_type.clone());
+    }
+
+    for (mime_type, concealed_value) in [
+        (KDE_PASSWORD_HINT, "secret"),
+        (CLIPBOARD_STATE, "sensitive"),
+    ] {
+        if !mime_types.iter().any(|m| m == mime_type) {
+            continue;
+        }
+        let value = backend
+            .read_data(mime_type)
+            .map(|data| String::from_utf8_lossy(&data).trim().to_string());
+        if value.as_deref() == Some(concealed_value) {
+            return Some(format!("{}={}", mime_type, concealed_value));
+        }
+    }
+    None
+}
+
+/// 轮询后端无法列出任意类型，只检查本平台约定的标记是否存在
+#[cfg(target_os = "macos")]
+pub fn platform_types() -> Vec<String> {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    unsafe {
+        let pasteboard: *mut Object = msg_send![class!(NSPasteboard), generalPasteboard];
+        if pasteboard.is_null() {
+            return Vec::new();
+        }
+        let types: *mut Object = msg_send![pasteboard, types];
+        if types.is_null() {
+            return Vec::new();
+        }
+        let count: usize = msg_send![types, count];
+        (0..count)
+            .filter_map(|i| {
+                let item: *mut Object = msg_send![types, objectAtIndex: i];
+                crate::focus::nsstring_to_string(item)
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn platform_types() -> Vec<String> {
+    use windows::core::HSTRING;
+    use windows::Win32::System::DataExchange::{
+        IsClipboardFormatAvailable, RegisterClipboardFormatW,
+    };
+
+    CONCEALED_TYPES
+        .iter()
+        .filter(|name| unsafe {
+            let format = RegisterClipboardFormatW(&HSTRING::from(**name));
+            format != 0 && IsClipboardFormatAvailable(format).is_ok()
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+pub fn platform_types() -> Vec<String> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::backend::MemoryBackend;
+
+    #[test]
+    fn kde_password_hint_is_concealed() {
+        let mut backend = MemoryBackend::default();
+        backend.set_format(KDE_PASSWORD_HINT, b"secret");
+        assert_eq!(
+            detect(&mut backend).as_deref(),
+            Some("x-kde-passwordManagerHint=secret")
+        );
+    }
+
+    #[test]
+    fn hint_with_other_value_is_not_concealed() {
+        let mut backend = MemoryBackend::default();
+        backend.set_format(KDE_PASSWORD_HINT, b"public");
+        backend.set_format(CLIPBOARD_STATE, b"normal");
+        assert_eq!(detect(&mut backend), None);
+    }
+
+    #[test]
+    fn clipboard_state_sensitive_is_concealed() {
+        let mut backend = MemoryBackend::default();
+        backend.set_format(CLIPBOARD_STATE, b"sensitive\n");
+        assert!(detect(&mut backend).is_some());
+    }
+
+    #[test]
+    fn marker_types_are_concealed_by_presence() {
+        for marker in CONCEALED_TYPES {
+            let mut backend = MemoryBackend::default();
+            backend.set_format(marker, b"");
+            assert_eq!(detect(&mut backend).as_deref(), Some(*marker));
+        }
+    }
+}
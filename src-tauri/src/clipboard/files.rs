@@ -0,0 +1,116 @@
+use crate::models::FileEntry;
+#[cfg(target_os = "linux")]
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use std::path::PathBuf;
+
+/// 文件 URI 中保留原样的字符：路径分隔符和 RFC 3986 的非保留字符
+#[cfg(target_os = "linux")]
+const URI_PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+#[cfg(target_os = "linux")]
+/// 解析 text/uri-list，忽略注释行和非本地文件 URI
+pub fn parse_uri_list(list: &str) -> Vec<PathBuf> {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let rest = line.strip_prefix("file://")?;
+            // file:///path 或 file://localhost/path
+            let path = rest.strip_prefix("localhost").unwrap_or(rest);
+            path.starts_with('/')
+                .then(|| PathBuf::from(percent_decode_str(path).decode_utf8_lossy().into_owned()))
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+/// 生成 text/uri-list，按规范使用 CRLF 分隔
+pub fn to_uri_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| format!("{}\r\n", file_uri(path)))
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+/// GNOME 系文件管理器识别的剪贴板格式
+pub fn to_gnome_copied_files(paths: &[PathBuf]) -> String {
+    let uris: Vec<String> = paths.iter().map(|path| file_uri(path)).collect();
+    format!("copy\n{}", uris.join("\n"))
+}
+
+/// 记录捕获时每个文件的大小和是否存在
+pub fn describe(paths: &[PathBuf]) -> Vec<FileEntry> {
+    paths
+        .iter()
+        .map(|path| {
+            let metadata = std::fs::metadata(path).ok();
+            FileEntry {
+                path: path.to_string_lossy().to_string(),
+                size: metadata
+                    .as_ref()
+                    .filter(|m| m.is_file())
+                    .map_or(0, |m| m.len() as i64),
+                exists: metadata.is_some(),
+                is_dir: metadata.is_some_and(|m| m.is_dir()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn file_uri(path: &Path) -> String {
+    format!(
+        "file://{}",
+        utf8_percent_encode(&path.to_string_lossy(), URI_PATH)
+    )
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uri_list_round_trips_special_characters() {
+        let paths = vec![
+            PathBuf::from("/home/user/报告 (final).pdf"),
+            PathBuf::from("/tmp/100%.txt"),
+        ];
+        let list = to_uri_list(&paths);
+        assert!(list.starts_with("file:///home/user/%E6%8A%A5%E5%91%8A%20%28final%29.pdf\r\n"));
+        assert_eq!(parse_uri_list(&list), paths);
+    }
+
+    #[test]
+    fn uri_list_skips_comments_and_remote_uris() {
+        let list = "# copied by nautilus\nfile://localhost/etc/hosts\nhttps://example.com/a\nsmb://server/share\n";
+        assert_eq!(parse_uri_list(list), [PathBuf::from("/etc/hosts")]);
+    }
+
+    #[test]
+    fn gnome_copied_files_starts_with_action() {
+        let paths = vec![PathBuf::from("/a"), PathBuf::from("/b c")];
+        assert_eq!(to_gnome_copied_files(&paths), "copy\nfile:///a\nfile:///b%20c");
+    }
+
+    #[test]
+    fn describe_records_size_and_existence() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, b"hello").unwrap();
+        let missing = dir.path().join("missing");
+
+        let entries = describe(&[file, dir.path().to_path_buf(), missing]);
+
+        assert_eq!((entries[0].size, entries[0].exists, entries[0].is_dir), (5, true, false));
+        assert_eq!((entries[1].size, entries[1].exists, entries[1].is_dir), (0, true, true));
+        assert_eq!((entries[2].size, entries[2].exists, entries[2].is_dir), (0, false, false));
+    }
+}
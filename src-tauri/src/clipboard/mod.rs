@@ -0,0 +1,188 @@
+mod backend;
+mod concealed;
+mod files;
+mod perceptual;
+mod persist;
+mod pipeline;
+mod secrets;
+mod snapshot;
+#[cfg(target_os = "linux")]
+mod wayland;
+mod writer;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use crate::crypto::EncryptionState;
+use crate::models::{ClipboardPayload, Settings};
+use crate::rules::AppRulesState;
+use crate::settings::SettingsState;
+use crate::storage::{self, HistoryStore};
+use persist::ImageWorkers;
+use pipeline::CapturePipeline;
+use std::path::Path;
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub use perceptual::distance as perceptual_distance;
+pub use persist::{save_thumbnail, temp_path, PendingImage};
+pub use pipeline::compute_hash;
+pub use secrets::default_patterns as default_secret_patterns;
+pub use snapshot::ClipboardSnapshot;
+pub use writer::{served_requests, wait_until_read, write_item, OwnWrites};
+
+pub struct ClipboardWatcher;
+
+impl ClipboardWatcher {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn start(&self, app: AppHandle) {
+        thread::spawn(move || {
+            let images_dir = match app.path().app_data_dir() {
+                Ok(dir) => dir.join("images"),
+                Err(e) => {
+                    eprintln!("Failed to resolve app data dir: {}", e);
+                    return;
+                }
+            };
+            let mut pipeline = CapturePipeline::new(images_dir);
+            app.manage(ImageWorkers::start(app.clone()));
+
+            let mut backend = match backend::select_backend() {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("Failed to create clipboard: {}", e);
+                    return;
+                }
+            };
+
+            loop {
+                if let Err(e) = backend.wait_for_change() {
+                    eprintln!("Clipboard backend failed, falling back to polling: {}", e);
+                    match backend::PollingBackend::new() {
+                        Ok(polling) => backend = Box::new(polling),
+                        Err(e) => {
+                            eprintln!("Failed to create clipboard: {}", e);
+                            return;
+                        }
+                    }
+                    continue;
+                }
+
+                // 临时粘贴期间的写入和恢复都不是用户的复制，不读取也不更新去重状态
+                let own_writes = app.state::<OwnWrites>();
+                if own_writes.is_paused() {
+                    continue;
+                }
+
+                let settings = app.state::<SettingsState>().get();
+                pipeline.configure_secrets(&settings);
+                pipeline.configure_images(&settings);
+                let store = app.state::<HistoryStore>();
+                let locked = app.state::<EncryptionState>().is_locked(&store);
+                pipeline.configure_encryption(store.cipher(), locked);
+                let max_size = settings.max_file_size_bytes();
+                let rules = app.state::<AppRulesState>();
+                let capture = pipeline.capture(backend.as_mut(), max_size, &own_writes, &rules);
+                if let Some(skipped) = capture.skipped {
+                    let _ = app.emit("clipboard-skipped", skipped);
+                }
+                let mut images = capture.images;
+                for payload in capture.items {
+                    let image = images
+                        .iter()
+                        .position(|job| job.hash == payload.content_hash)
+                        .map(|index| images.swap_remove(index));
+                    store_capture(&app, payload, image);
+                }
+            }
+        });
+    }
+}
+
+/// 写入历史存储并通知前端刷新，再把图片交给后台写出。加密的历史尚未解锁时
+/// 条目和图片都先暂存，解锁后再写入。文件还在后台写入的图片条目以 pending 发出，
+/// 写完后另有 image-ready 事件
+pub fn store_capture(app: &AppHandle, mut payload: ClipboardPayload, image: Option<PendingImage>) {
+    let store = app.state::<HistoryStore>();
+    let encryption = app.state::<EncryptionState>();
+    if encryption.is_locked(&store) {
+        encryption.defer(payload, image);
+        return;
+    }
+    // 开启加密时图片文件按带密钥的 hash 命名，相同内容的文件已存在时不再写出
+    let image = image.and_then(|mut job| {
+        job.rename(&store.content_key(&job.hash));
+        payload.image_path = Some(job.original.to_string_lossy().to_string());
+        payload.thumbnail_path = Some(job.thumbnail.to_string_lossy().to_string());
+        (!job.is_saved()).then_some(job)
+    });
+    let settings = app.state::<SettingsState>().get();
+    let merged = merge_similar(&store, &settings, &mut payload);
+    // 查询失败时按已存在处理，图片写入失败也不会删掉原有条目
+    let existed = store.contains_hash(&payload.content_hash).unwrap_or(true);
+    let mut inserted = None;
+    match store.upsert(&payload) {
+        Ok(item) => {
+            inserted = (!existed).then_some(item.id);
+            if let Err(e) =
+                store.apply_retention(settings.max_items as u32, settings.max_days as u32)
+            {
+                eprintln!("Failed to apply retention: {}", e);
+            }
+            let mut item = storage::mask_sensitive(item);
+            item.pending = item.content_type == "image"
+                && item
+                    .image_path
+                    .as_deref()
+                    .is_some_and(|path| !Path::new(path).exists());
+            let _ = app.emit("clipboard-changed", item);
+        }
+        Err(e) => eprintln!("Failed to store clipboard item: {}", e),
+    }
+    // 合并到相似图片的条目不再需要写入自己的文件
+    if let Some(mut job) = image.filter(|_| !merged) {
+        job.inserted = inserted;
+        if let Some(workers) = app.try_state::<ImageWorkers>() {
+            workers.submit(app, job);
+        }
+    }
+}
+
+/// 开启相似图片合并时，把与已有图片足够相似的捕获改为指向那条记录，
+/// 入库时只刷新它的时间。返回是否发生了合并
+fn merge_similar(
+    store: &HistoryStore,
+    settings: &Settings,
+    payload: &mut ClipboardPayload,
+) -> bool {
+    let Some(perceptual_hash) = payload
+        .perceptual_hash
+        .as_deref()
+        .filter(|_| settings.merge_similar_images)
+    else {
+        return false;
+    };
+    let max_distance = settings.similar_image_distance.max(0) as u32;
+    match store.similar_image(&payload.content_hash, perceptual_hash, max_distance) {
+        Ok(Some(existing)) => {
+            // 解锁前暂存的捕获可能已经写出了自己的文件
+            store.delete_files(
+                payload.image_path.as_deref(),
+                payload.thumbnail_path.as_deref(),
+            );
+            payload.content_hash = existing.content_hash;
+            payload.image_path = existing.image_path;
+            payload.thumbnail_path = existing.thumbnail_path;
+            payload.file_size = existing.file_size;
+            payload.perceptual_hash = None;
+            true
+        }
+        Ok(None) => false,
+        Err(e) => {
+            eprintln!("Failed to look up similar images: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use image::{imageops, ImageBuffer, Rgba};
+
+/// 计算 64 位 dHash：缩小到 9x8 灰度图，逐行比较相邻像素的明暗。
+/// 重新截图、换应用复制等字节不同但看起来一样的图片得到相同或相近的值
+pub fn dhash(width: u32, height: u32, rgba: &[u8]) -> Option<String> {
+    let img = ImageBuffer::<Rgba<u8>, &[u8]>::from_raw(width, height, rgba)?;
+    let small = imageops::thumbnail(&img, 9, 8);
+    let luma = |x: u32, y: u32| {
+        let [r, g, b, _] = small.get_pixel(x, y).0;
+        r as u32 * 299 + g as u32 * 587 + b as u32 * 114
+    };
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | (luma(x, y) > luma(x + 1, y)) as u64;
+        }
+    }
+    Some(format!("{hash:016x}"))
+}
+
+/// 两个 dHash 不同的位数，无法解析时返回 None
+pub fn distance(a: &str, b: &str) -> Option<u32> {
+    let a = u64::from_str_radix(a, 16).ok()?;
+    let b = u64::from_str_radix(b, 16).ok()?;
+    Some((a ^ b).count_ones())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ClipboardPayload;
+    use crate::storage::HistoryStore;
+
+    /// 水平渐变，rising 为 false 时从左到右由亮变暗，offset 整体调整亮度
+    fn gradient(width: u32, height: u32, offset: u8, rising: bool) -> Vec<u8> {
+        (0..height)
+            .flat_map(|_| 0..width)
+            .flat_map(|x| {
+                let x = if rising { x } else { width - 1 - x };
+                let v = (x * 200 / width) as u8 + offset;
+                [v, v, v, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn slightly_changed_images_are_close() {
+        let original = dhash(320, 240, &gradient(320, 240, 0, true)).unwrap();
+        let brighter = dhash(320, 240, &gradient(320, 240, 20, true)).unwrap();
+        // 同一画面不同分辨率
+        let resized = dhash(160, 120, &gradient(160, 120, 0, true)).unwrap();
+        assert!(distance(&original, &brighter).unwrap() <= 4);
+        assert!(distance(&original, &resized).unwrap() <= 4);
+    }
+
+    #[test]
+    fn different_images_are_far_apart() {
+        let rising = dhash(320, 240, &gradient(320, 240, 0, true)).unwrap();
+        let falling = dhash(320, 240, &gradient(320, 240, 0, false)).unwrap();
+        assert!(distance(&rising, &falling).unwrap() > 32);
+    }
+
+    #[test]
+    fn closest_image_within_distance_is_found_in_history() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let image = |hash: &str, perceptual_hash: &str| ClipboardPayload {
+            content_type: "image".to_string(),
+            content_hash: hash.to_string(),
+            image_path: Some(format!("/images/{hash}.png")),
+            perceptual_hash: Some(perceptual_hash.to_string()),
+            ..Default::default()
+        };
+        store.upsert(&image("far", "ffffffffffffffff")).unwrap();
+        let near = store.upsert(&image("near", "0000000000000003")).unwrap();
+
+        let found = store.similar_image("new", "0000000000000001", 4).unwrap();
+        assert_eq!(found.map(|item| item.id), Some(near.id));
+        assert!(store
+            .similar_image("new", "00000000000000ff", 4)
+            .unwrap()
+            .is_none());
+        // 内容完全相同的捕获直接刷新原条目
+        assert!(store
+            .similar_image("near", "0000000000000003", 4)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn mismatched_buffer_has_no_hash() {
+        assert!(dhash(10, 10, &[0; 16]).is_none());
+        assert_eq!(distance("zz", "00"), None);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::budget;
+use crate::models::ImageReady;
+use crate::settings::SettingsState;
+use crate::storage::HistoryStore;
+use arboard::ImageData;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{DynamicImage, ImageBuffer, Rgba};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 同时编码图片的线程数
+const WORKERS: usize = 2;
+/// 排队等待写入的图片上限，满了之后由监听线程自己写入
+const QUEUE_CAPACITY: usize = 4;
+
+/// 临时文件序号，同一文件可能由多个线程同时写入
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 原图按内容 hash 命名，相同内容的捕获共用一份文件
+pub fn original_path(images_dir: &Path, hash: &str) -> PathBuf {
+    images_dir.join(format!("{hash}.png"))
+}
+
+/// 缩略图由原图文件名派生，无需查询数据库即可找到
+pub fn thumbnail_path(images_dir: &Path, key: &str) -> PathBuf {
+    images_dir.join(format!("{key}_thumb.png"))
+}
+
+/// Generate and save thumbnail (200x200 max)
+pub fn save_thumbnail(images_dir: &Path, key: &str, img: DynamicImage) -> Result<String, String> {
+    let thumb_path = thumbnail_path(images_dir, key);
+    if !thumb_path.exists() {
+        write_png(&thumb_path, &img.thumbnail(200, 200))?;
+    }
+    Ok(thumb_path.to_string_lossy().to_string())
+}
+
+/// 与 path 同目录、每次调用都不同的临时文件名，写完后再改名为 path
+pub fn temp_path(path: &Path) -> PathBuf {
+    let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{name}.{}.{seq}.tmp", std::process::id()))
+}
+
+/// 先写临时文件再改名，中断时不会留下以 hash 命名的半个文件。
+/// 截图体积大，用快速压缩换取编码速度
+fn write_png(path: &Path, img: &DynamicImage) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let tmp = temp_path(path);
+    let file = File::create(&tmp).map_err(|e| e.to_string())?;
+    let encoder = PngEncoder::new_with_quality(
+        BufWriter::new(file),
+        CompressionType::Fast,
+        FilterType::Adaptive,
+    );
+    img.write_with_encoder(encoder).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+/// 已入库但文件还没写出的图片
+pub struct PendingImage {
+    pub hash: String,
+    pub original: PathBuf,
+    pub thumbnail: PathBuf,
+    /// 本次捕获新插入的条目。写入失败时只撤回它，不删除被刷新的已有条目
+    pub inserted: Option<i64>,
+    image: ImageData<'static>,
+}
+
+impl PendingImage {
+    pub fn new(images_dir: &Path, hash: String, image: ImageData<'static>) -> Self {
+        Self {
+            original: original_path(images_dir, &hash),
+            thumbnail: thumbnail_path(images_dir, &hash),
+            inserted: None,
+            hash,
+            image,
+        }
+    }
+
+    /// 改用 key 命名文件，开启加密时 key 为带密钥的 hash
+    pub fn rename(&mut self, key: &str) {
+        let dir = self
+            .original
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        self.original = original_path(&dir, key);
+        self.thumbnail = thumbnail_path(&dir, key);
+    }
+
+    /// 相同内容的文件已存在（可能已加密）时直接复用
+    pub fn is_saved(&self) -> bool {
+        self.original.exists() && self.thumbnail.exists()
+    }
+
+    /// 编码并写出原图和缩略图
+    pub fn save(&self) -> Result<(), String> {
+        let img: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::from_raw(
+            self.image.width as u32,
+            self.image.height as u32,
+            self.image.bytes.to_vec(),
+        )
+        .ok_or("Failed to create image buffer")?;
+        let img = DynamicImage::ImageRgba8(img);
+
+        if !self.original.exists() {
+            write_png(&self.original, &img)?;
+        }
+        if !self.thumbnail.exists() {
+            write_png(&self.thumbnail, &img.thumbnail(200, 200))?;
+        }
+        Ok(())
+    }
+}
+
+/// 固定数量的后台线程，负责把捕获到的图片写入磁盘
+pub struct ImageWorkers {
+    sender: SyncSender<PendingImage>,
+}
+
+impl ImageWorkers {
+    pub fn start(app: AppHandle) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_CAPACITY);
+        let receiver: Arc<Mutex<Receiver<PendingImage>>> = Arc::new(Mutex::new(receiver));
+        for _ in 0..WORKERS {
+            let app = app.clone();
+            let receiver = receiver.clone();
+            thread::spawn(move || loop {
+                let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                    Ok(job) => job,
+                    Err(_) => return,
+                };
+                finish(&app, job);
+            });
+        }
+        Self { sender }
+    }
+
+    /// 交给后台写入。队列已满时在当前线程写入，避免积压的图片无限占用内存
+    pub fn submit(&self, app: &AppHandle, job: PendingImage) {
+        match self.sender.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(job) | TrySendError::Disconnected(job)) => finish(app, job),
+        }
+    }
+}
+
+/// 写出文件后按需加密并检查图片空间；写入失败时撤回已入库的条目。
+/// 完成后发出 image-ready 事件
+fn finish(app: &AppHandle, job: PendingImage) {
+    let store = app.state::<HistoryStore>();
+    let error = match job.save() {
+        Ok(()) => {
+            for path in [&job.original, &job.thumbnail] {
+                if let Err(e) = store.protect_file(path) {
+                    eprintln!("Failed to encrypt image file: {}", e);
+                }
+            }
+            None
+        }
+        Err(e) => {
+            eprintln!("Failed to save image: {}", e);
+            match job.inserted.map(|id| store.delete(id)) {
+                Some(Ok(Some(item))) => {
+                    store.delete_files(item.image_path.as_deref(), item.thumbnail_path.as_deref())
+                }
+                Some(Err(e)) => eprintln!("Failed to remove unsaved image: {}", e),
+                _ => {}
+            }
+            Some(e)
+        }
+    };
+    let saved = error.is_none();
+    let _ = app.emit(
+        "image-ready",
+        ImageReady {
+            content_hash: job.hash,
+            error,
+        },
+    );
+    if saved {
+        let quota = app.state::<SettingsState>().get().max_image_storage_bytes();
+        budget::enforce_and_report(app, &store, quota);
+    }
+}
@@ -0,0 +1,187 @@
+use super::backend::{self, ClipboardBackend};
+use crate::models::ClipboardFormat;
+use arboard::ImageData;
+use image::{ImageFormat, RgbaImage};
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// 临时粘贴前剪贴板的全部内容，粘贴完成后原样写回
+pub struct ClipboardSnapshot {
+    formats: Vec<ClipboardFormat>,
+    /// 没有 text/uri-list 的平台单独保存文件列表，写回时交给 arboard
+    files: Vec<PathBuf>,
+    /// 来源程序标记为敏感的内容。写回时无法保留标记，密码会被当作普通复制记录，
+    /// 也躲过密码管理器的定时清除，因此不读取也不写回
+    concealed: bool,
+}
+
+impl ClipboardSnapshot {
+    /// 用新的后端连接读取当前剪贴板，不影响监听线程的状态
+    pub fn take() -> Result<Self, String> {
+        let mut backend = backend::select_backend()?;
+        Ok(Self::read(backend.as_mut()))
+    }
+
+    /// 读取所有 MIME 类型的数据。X11 的 UTF8_STRING 等非 MIME 目标和
+    /// 只能通过 arboard 读取的平台用纯文本和图片补齐
+    fn read(backend: &mut dyn ClipboardBackend) -> Self {
+        if super::concealed::detect(backend).is_some() {
+            return Self {
+                formats: Vec::new(),
+                files: Vec::new(),
+                concealed: true,
+            };
+        }
+        let mut formats: Vec<ClipboardFormat> = backend
+            .mime_types()
+            .into_iter()
+            .filter(|mime_type| mime_type.contains('/'))
+            .filter_map(|mime_type| {
+                backend
+                    .read_data(&mime_type)
+                    .map(|data| ClipboardFormat { mime_type, data })
+            })
+            .collect();
+        let has = |formats: &[ClipboardFormat], prefix: &str| {
+            formats.iter().any(|f| f.mime_type.starts_with(prefix))
+        };
+
+        let mut files = Vec::new();
+        if !has(&formats, "text/uri-list") {
+            files = backend.read_files();
+            #[cfg(target_os = "linux")]
+            if !files.is_empty() {
+                formats.push(ClipboardFormat {
+                    mime_type: "text/uri-list".to_string(),
+                    data: super::files::to_uri_list(&std::mem::take(&mut files)).into_bytes(),
+                });
+            }
+        }
+        if !has(&formats, "text/html") {
+            formats.extend(
+                backend
+                    .read_formats()
+                    .into_iter()
+                    .filter(|f| f.mime_type == "text/html"),
+            );
+        }
+        if !has(&formats, "text/plain") {
+            if let Some(text) = backend.read_text() {
+                formats.push(ClipboardFormat {
+                    mime_type: "text/plain;charset=utf-8".to_string(),
+                    data: text.into_bytes(),
+                });
+            }
+        }
+        if !has(&formats, "image/") {
+            if let Some(png) = backend.read_image().and_then(encode_png) {
+                formats.push(ClipboardFormat {
+                    mime_type: "image/png".to_string(),
+                    data: png,
+                });
+            }
+        }
+        Self {
+            formats,
+            files,
+            concealed: false,
+        }
+    }
+
+    /// 写回快照，快照为空时清空剪贴板。敏感内容不写回，剪贴板保留粘贴的条目
+    pub fn restore(self) -> Result<(), String> {
+        if self.concealed {
+            return Ok(());
+        }
+        super::writer::write_formats(self.formats, self.files)
+    }
+}
+
+fn encode_png(image: ImageData) -> Option<Vec<u8>> {
+    let rgba = RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into())?;
+    let mut png = Vec::new();
+    rgba.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::backend::MemoryBackend;
+
+    fn mime_types(snapshot: &ClipboardSnapshot) -> Vec<&str> {
+        snapshot
+            .formats
+            .iter()
+            .map(|f| f.mime_type.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_every_mime_type_and_skips_x11_targets() {
+        let mut backend = MemoryBackend::default();
+        backend.set_format("text/html", b"<b>hi</b>");
+        backend.set_format("application/x-custom", b"\x00\x01");
+        backend.set_format("TARGETS", b"");
+        backend.set_text("hi");
+
+        let snapshot = ClipboardSnapshot::read(&mut backend);
+        assert_eq!(
+            mime_types(&snapshot),
+            [
+                "text/html",
+                "application/x-custom",
+                "text/plain;charset=utf-8"
+            ]
+        );
+        assert_eq!(snapshot.formats[1].data, b"\x00\x01");
+    }
+
+    #[test]
+    fn images_without_a_mime_type_are_encoded_as_png() {
+        let mut backend = MemoryBackend::default();
+        backend.set_image(1, 1, vec![255, 0, 0, 255]);
+
+        let snapshot = ClipboardSnapshot::read(&mut backend);
+        assert_eq!(mime_types(&snapshot), ["image/png"]);
+        let decoded = image::load_from_memory(&snapshot.formats[0].data).unwrap();
+        assert_eq!(decoded.to_rgba8().into_raw(), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn empty_clipboard_gives_an_empty_snapshot() {
+        let snapshot = ClipboardSnapshot::read(&mut MemoryBackend::default());
+        assert!(snapshot.formats.is_empty() && !snapshot.concealed);
+    }
+
+    #[test]
+    fn file_lists_are_kept_for_restore() {
+        let mut backend = MemoryBackend::default();
+        let files = vec![PathBuf::from("/tmp/a b.txt")];
+        backend.set_files(&files);
+
+        let snapshot = ClipboardSnapshot::read(&mut backend);
+        // Linux 下以 text/uri-list 写回，其他平台单独交给 arboard
+        #[cfg(target_os = "linux")]
+        {
+            assert!(snapshot.files.is_empty());
+            assert!(mime_types(&snapshot).contains(&"text/uri-list"));
+        }
+        #[cfg(not(target_os = "linux"))]
+        assert_eq!(snapshot.files, files);
+    }
+
+    #[test]
+    fn concealed_passwords_are_not_kept_for_restore() {
+        let mut backend = MemoryBackend::default();
+        backend.set_format("x-kde-passwordManagerHint", b"secret");
+        backend.set_text("hunter2");
+
+        let snapshot = ClipboardSnapshot::read(&mut backend);
+        assert!(snapshot.concealed);
+        assert!(snapshot.formats.is_empty());
+        // 写回时什么也不做，不会把去掉标记的密码放回剪贴板
+        snapshot.restore().unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+use super::backend::{is_extra_format, ClipboardBackend};
+use crate::models::{ClipboardFormat, SourceApp};
+use arboard::ImageData;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use wayland_client::backend::ObjectId;
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{
+    event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::data_control::v1::client::{
+    ext_data_control_device_v1 as ext_device, ext_data_control_manager_v1 as ext_manager,
+    ext_data_control_offer_v1 as ext_offer,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1 as wlr_device, zwlr_data_control_manager_v1 as wlr_manager,
+    zwlr_data_control_offer_v1 as wlr_offer,
+};
+use wl_clipboard_rs::copy::{self, MimeSource, MimeType, Source};
+
+/// 读取 offer 数据的超时时间，避免源程序不响应时卡住监听线程
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 按优先级排列的纯文本 MIME 类型
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "TEXT",
+    "STRING",
+];
+
+/// Wayland 后端：通过 ext-data-control-v1 或 wlr-data-control-unstable-v1
+/// 接收选区 offer，窗口不在前台时也能看到剪贴板变化
+pub struct WaylandBackend {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    first: bool,
+}
+
+/// 两个协议的接口几乎一致，用枚举统一处理
+enum Offer {
+    Ext(ext_offer::ExtDataControlOfferV1),
+    Wlr(wlr_offer::ZwlrDataControlOfferV1),
+}
+
+impl Offer {
+    fn id(&self) -> ObjectId {
+        match self {
+            Self::Ext(offer) => offer.id(),
+            Self::Wlr(offer) => offer.id(),
+        }
+    }
+
+    fn receive(&self, mime_type: &str, fd: BorrowedFd<'_>) {
+        match self {
+            Self::Ext(offer) => offer.receive(mime_type.to_string(), fd),
+            Self::Wlr(offer) => offer.receive(mime_type.to_string(), fd),
+        }
+    }
+
+    fn destroy(&self) {
+        match self {
+            Self::Ext(offer) => offer.destroy(),
+            Self::Wlr(offer) => offer.destroy(),
+        }
+    }
+}
+
+/// 当前剪贴板选区
+struct Selection {
+    offer: Offer,
+    mime_types: Vec<String>,
+}
+
+#[derive(Default)]
+struct State {
+    seat: Option<wl_seat::WlSeat>,
+    ext_manager: Option<ext_manager::ExtDataControlManagerV1>,
+    wlr_manager: Option<wlr_manager::ZwlrDataControlManagerV1>,
+    /// 尚未成为选区的 offer 及其已公布的 MIME 类型
+    pending: HashMap<ObjectId, Vec<String>>,
+    selection: Option<Selection>,
+    changed: bool,
+    finished: bool,
+}
+
+impl State {
+    fn set_selection(&mut self, offer: Option<Offer>) {
+        if let Some(old) = self.selection.take() {
+            old.offer.destroy();
+        }
+        // 选区被清空时没有新内容可捕获
+        if let Some(offer) = offer {
+            let mime_types = self.pending.remove(&offer.id()).unwrap_or_default();
+            self.selection = Some(Selection { offer, mime_types });
+            self.changed = true;
+        }
+    }
+
+    fn discard(&mut self, offer: Offer) {
+        self.pending.remove(&offer.id());
+        offer.destroy();
+    }
+}
+
+impl WaylandBackend {
+    pub fn new() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|e| e.to_string())?;
+        let mut queue = conn.new_event_queue();
+        let qh = queue.handle();
+        let mut state = State::default();
+
+        conn.display().get_registry(&qh, ());
+        queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+
+        let seat = state.seat.as_ref().ok_or("No wl_seat advertised")?;
+        if let Some(manager) = state.ext_manager.as_ref() {
+            manager.get_data_device(seat, &qh, ());
+        } else if let Some(manager) = state.wlr_manager.as_ref() {
+            manager.get_data_device(seat, &qh, ());
+        } else {
+            return Err("Compositor supports neither ext-data-control-v1 nor wlr-data-control".into());
+        }
+
+        // 再往返一次以拿到当前选区
+        queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            conn,
+            queue,
+            state,
+            first: true,
+        })
+    }
+
+    /// 读取当前选区中指定 MIME 类型的数据
+    fn receive(&self, mime_type: &str) -> Option<Vec<u8>> {
+        let selection = self.state.selection.as_ref()?;
+        let (reader, writer) = std::io::pipe().ok()?;
+        selection.offer.receive(mime_type, writer.as_fd());
+        self.conn.flush().ok()?;
+        // 关闭本端写入端，源程序写完后读取端才能读到 EOF
+        drop(writer);
+        read_with_timeout(reader, RECEIVE_TIMEOUT)
+    }
+
+    fn offered(&self, mime_type: &str) -> bool {
+        self.state
+            .selection
+            .as_ref()
+            .is_some_and(|s| s.mime_types.iter().any(|m| m == mime_type))
+    }
+}
+
+impl ClipboardBackend for WaylandBackend {
+    fn wait_for_change(&mut self) -> Result<(), String> {
+        // 启动时立即读取一次已有内容
+        if std::mem::take(&mut self.first) {
+            self.state.changed = false;
+            return Ok(());
+        }
+
+        while !self.state.changed {
+            self.queue
+                .blocking_dispatch(&mut self.state)
+                .map_err(|e| e.to_string())?;
+            if self.state.finished {
+                return Err("Data control device was finished by the compositor".into());
+            }
+        }
+        self.state.changed = false;
+        Ok(())
+    }
+
+    fn read_text(&mut self) -> Option<String> {
+        let mime_type = TEXT_MIME_TYPES.iter().find(|m| self.offered(m))?;
+        String::from_utf8(self.receive(mime_type)?).ok()
+    }
+
+    fn read_image(&mut self) -> Option<ImageData<'static>> {
+        let selection = self.state.selection.as_ref()?;
+        let mime_type = selection
+            .mime_types
+            .iter()
+            .find(|m| *m == "image/png")
+            .or_else(|| selection.mime_types.iter().find(|m| m.starts_with("image/")))?
+            .clone();
+
+        let bytes = self.receive(&mime_type)?;
+        let rgba = image::load_from_memory(&bytes).ok()?.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        Some(ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::Owned(rgba.into_raw()),
+        })
+    }
+
+    fn read_formats(&mut self) -> Vec<ClipboardFormat> {
+        let Some(selection) = self.state.selection.as_ref() else {
+            return Vec::new();
+        };
+        let mime_types: Vec<String> = selection
+            .mime_types
+            .iter()
+            .filter(|m| is_extra_format(m))
+            .cloned()
+            .collect();
+
+        mime_types
+            .into_iter()
+            .filter_map(|mime_type| {
+                let data = self.receive(&mime_type)?;
+                Some(ClipboardFormat { mime_type, data })
+            })
+            .collect()
+    }
+
+    fn read_files(&mut self) -> Vec<PathBuf> {
+        if !self.offered("text/uri-list") {
+            return Vec::new();
+        }
+        self.receive("text/uri-list")
+            .and_then(|data| String::from_utf8(data).ok())
+            .map(|list| super::files::parse_uri_list(&list))
+            .unwrap_or_default()
+    }
+
+    fn mime_types(&mut self) -> Vec<String> {
+        self.state
+            .selection
+            .as_ref()
+            .map(|s| s.mime_types.clone())
+            .unwrap_or_default()
+    }
+
+    fn read_data(&mut self, mime_type: &str) -> Option<Vec<u8>> {
+        if !self.offered(mime_type) {
+            return None;
+        }
+        self.receive(mime_type)
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        // data-control 不暴露来源客户端，XWayland 的激活窗口也未必是复制内容的程序
+        None
+    }
+}
+
+/// 通过 data-control 把多种表示形式同时写回剪贴板，后台线程负责响应粘贴请求。
+/// primary 为 true 时同时设置 PRIMARY 选区
+pub fn set_selection(formats: Vec<ClipboardFormat>, primary: bool) -> Result<(), String> {
+    let sources = formats
+        .into_iter()
+        .map(|format| MimeSource {
+            source: Source::Bytes(format.data.into_boxed_slice()),
+            mime_type: MimeType::Specific(format.mime_type),
+        })
+        .collect();
+    let clipboard = if primary {
+        copy::ClipboardType::Both
+    } else {
+        copy::ClipboardType::Regular
+    };
+    let mut options = copy::Options::new();
+    options.clipboard(clipboard);
+    options.copy_multi(sources).map_err(|e| e.to_string())
+}
+
+/// 非阻塞地读到 EOF，超过 timeout 时放弃并关闭管道，源程序不响应时不会留下阻塞的线程
+fn read_with_timeout(mut reader: std::io::PipeReader, timeout: Duration) -> Option<Vec<u8>> {
+    let fd = reader.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0 {
+        return None;
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut data = Vec::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let remaining = deadline.checked_duration_since(Instant::now())?;
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = remaining.as_millis().clamp(1, i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut pollfd, 1, timeout_ms) } {
+            0 => return None,
+            n if n < 0 => {
+                if std::io::Error::last_os_error().kind() != ErrorKind::Interrupted {
+                    return None;
+                }
+            }
+            _ => match reader.read(&mut buf) {
+                Ok(0) => return Some(data),
+                Ok(n) => data.extend_from_slice(&buf[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {}
+                Err(_) => return None,
+            },
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            match interface.as_str() {
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, version.min(2), qh, ()));
+                }
+                "ext_data_control_manager_v1" => {
+                    state.ext_manager = Some(registry.bind(name, 1, qh, ()));
+                }
+                "zwlr_data_control_manager_v1" => {
+                    state.wlr_manager = Some(registry.bind(name, version.min(2), qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+/// 为一组 data-control 接口（ext 或 wlr）实现事件分发
+macro_rules! data_control_dispatch {
+    ($variant:ident, $manager_mod:ident :: $manager:ident, $device_mod:ident :: $device:ident, $offer_mod:ident :: $offer:ident) => {
+        impl Dispatch<$manager_mod::$manager, ()> for State {
+            fn event(
+                _: &mut Self,
+                _: &$manager_mod::$manager,
+                _: $manager_mod::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+            }
+        }
+
+        impl Dispatch<$device_mod::$device, ()> for State {
+            fn event(
+                state: &mut Self,
+                _: &$device_mod::$device,
+                event: $device_mod::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                match event {
+                    $device_mod::Event::DataOffer { id } => {
+                        state.pending.insert(id.id(), Vec::new());
+                    }
+                    $device_mod::Event::Selection { id } => {
+                        state.set_selection(id.map(Offer::$variant));
+                    }
+                    // 只关心 CLIPBOARD，PRIMARY 选区直接丢弃
+                    $device_mod::Event::PrimarySelection { id: Some(offer) } => {
+                        state.discard(Offer::$variant(offer));
+                    }
+                    $device_mod::Event::Finished => state.finished = true,
+                    _ => {}
+                }
+            }
+
+            event_created_child!(State, $device_mod::$device, [
+                $device_mod::EVT_DATA_OFFER_OPCODE => ($offer_mod::$offer, ()),
+            ]);
+        }
+
+        impl Dispatch<$offer_mod::$offer, ()> for State {
+            fn event(
+                state: &mut Self,
+                offer: &$offer_mod::$offer,
+                event: $offer_mod::Event,
+                _: &(),
+                _: &Connection,
+                _: &QueueHandle<Self>,
+            ) {
+                if let $offer_mod::Event::Offer { mime_type } = event {
+                    state.pending.entry(offer.id()).or_default().push(mime_type);
+                }
+            }
+        }
+    };
+}
+
+data_control_dispatch!(
+    Ext,
+    ext_manager::ExtDataControlManagerV1,
+    ext_device::ExtDataControlDeviceV1,
+    ext_offer::ExtDataControlOfferV1
+);
+data_control_dispatch!(
+    Wlr,
+    wlr_manager::ZwlrDataControlManagerV1,
+    wlr_device::ZwlrDataControlDeviceV1,
+    wlr_offer::ZwlrDataControlOfferV1
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::process::Command;
+
+    #[test]
+    fn pipe_reads_stop_at_the_deadline() {
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        writer.write_all(b"hello").unwrap();
+        drop(writer);
+        assert_eq!(
+            read_with_timeout(reader, Duration::from_secs(1)).as_deref(),
+            Some(&b"hello"[..])
+        );
+
+        // 写入端一直不关闭时按时返回，而不是阻塞在 read 上
+        let (reader, mut writer) = std::io::pipe().unwrap();
+        writer.write_all(b"partial").unwrap();
+        let started = Instant::now();
+        assert_eq!(read_with_timeout(reader, Duration::from_millis(50)), None);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    /// 需要支持 data-control 的合成器和 wl-clipboard，例如：
+    /// `weston --backend=headless --socket=wayland-test &`
+    /// `WAYLAND_DISPLAY=wayland-test cargo test -- --ignored`
+    #[test]
+    #[ignore = "requires a Wayland compositor with data-control and wl-copy"]
+    fn captures_selection_set_by_another_client() {
+        let mut backend = WaylandBackend::new().expect("connect to compositor");
+        backend.wait_for_change().unwrap();
+
+        let status = Command::new("wl-copy")
+            .arg("super paste wayland test")
+            .status()
+            .expect("run wl-copy");
+        assert!(status.success());
+
+        backend.wait_for_change().unwrap();
+        assert_eq!(
+            backend.read_text().as_deref(),
+            Some("super paste wayland test")
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::models::{ClipboardFormat, ClipboardItem};
+use arboard::Clipboard;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 写回后等待监听线程看到这次写入的最长时间，过期后相同内容按用户复制处理
+const OWN_WRITE_TTL: Duration = Duration::from_secs(5);
+
+/// Super Paste 自己写入剪贴板的内容 hash，监听线程据此跳过重新捕获
+#[derive(Default)]
+pub struct OwnWrites {
+    hashes: Mutex<Vec<(String, Instant)>>,
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl OwnWrites {
+    pub fn record(&self, hash: String) {
+        let mut hashes = self.hashes.lock().unwrap_or_else(|e| e.into_inner());
+        hashes.retain(|(_, at)| at.elapsed() < OWN_WRITE_TTL);
+        hashes.push((hash, Instant::now()));
+    }
+
+    /// 是自己写入的内容时返回 true，每次写入只抵消一次捕获
+    pub fn take(&self, hash: &str) -> bool {
+        let mut hashes = self.hashes.lock().unwrap_or_else(|e| e.into_inner());
+        hashes.retain(|(_, at)| at.elapsed() < OWN_WRITE_TTL);
+        match hashes.iter().position(|(h, _)| h == hash) {
+            Some(index) => {
+                hashes.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 在 duration 内让监听线程忽略所有变化，用于临时粘贴期间的中间状态
+    pub fn pause(&self, duration: Duration) {
+        *self.paused_until.lock().unwrap_or_else(|e| e.into_inner()) =
+            Some(Instant::now() + duration);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_until
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some_and(|until| Instant::now() < until)
+    }
+}
+
+/// 把历史条目写回剪贴板，额外格式与文本/图片同时提供，粘贴时保留原有格式。
+/// image 为图片条目已解密的原图 PNG 数据，primary 为 true 时同时写入 PRIMARY 选区（仅 Linux）
+pub fn write_item(
+    item: &ClipboardItem,
+    formats: Vec<ClipboardFormat>,
+    image: Option<Vec<u8>>,
+    primary: bool,
+    own_writes: &OwnWrites,
+) -> Result<(), String> {
+    // 先登记再写入，避免监听线程抢先读到
+    own_writes.record(item.content_hash.clone());
+
+    // 文件列表需要 text/uri-list 等多个目标，arboard 只在部分平台上提供。
+    // Linux 下总是自行持有选区，X11 下还能得知目标应用何时读完
+    #[cfg(target_os = "linux")]
+    match write_all_formats(item, &formats, image.as_deref(), primary) {
+        Ok(()) => return Ok(()),
+        Err(e) => eprintln!("Failed to restore all clipboard formats: {}", e),
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = primary;
+    write_with_arboard(item, &formats, image.as_deref())
+}
+
+/// 本进程写入的内容已被其他程序读取的次数，只有 X11 下能够统计，其他情况始终为 0
+pub fn served_requests() -> u64 {
+    #[cfg(target_os = "linux")]
+    return super::x11::served_requests();
+    #[cfg(not(target_os = "linux"))]
+    0
+}
+
+/// 等待其他程序读取 since 之后写入的内容，最多等待 timeout。
+/// 无法得知何时读取的平台总是等满 timeout
+pub fn wait_until_read(since: u64, timeout: Duration) {
+    #[cfg(target_os = "linux")]
+    if !crate::focus::is_wayland_session() {
+        super::x11::wait_for_request(since, timeout);
+        return;
+    }
+    let _ = since;
+    std::thread::sleep(timeout);
+}
+
+/// Linux 下自行持有选区，可以提供任意 MIME 类型
+#[cfg(target_os = "linux")]
+fn write_all_formats(
+    item: &ClipboardItem,
+    formats: &[ClipboardFormat],
+    image: Option<&[u8]>,
+    primary: bool,
+) -> Result<(), String> {
+    let mut all = Vec::with_capacity(formats.len() + 1);
+    match item.content_type.as_str() {
+        "text" => all.push(ClipboardFormat {
+            mime_type: "text/plain;charset=utf-8".to_string(),
+            data: item.text_content.clone().unwrap_or_default().into_bytes(),
+        }),
+        "image" => all.push(ClipboardFormat {
+            mime_type: "image/png".to_string(),
+            data: image.ok_or("Image file is missing")?.to_vec(),
+        }),
+        "files" => {
+            let paths = file_paths(item);
+            all.push(ClipboardFormat {
+                mime_type: "text/uri-list".to_string(),
+                data: super::files::to_uri_list(&paths).into_bytes(),
+            });
+            all.push(ClipboardFormat {
+                mime_type: "x-special/gnome-copied-files".to_string(),
+                data: super::files::to_gnome_copied_files(&paths).into_bytes(),
+            });
+            all.push(ClipboardFormat {
+                mime_type: "text/plain;charset=utf-8".to_string(),
+                data: item.text_content.clone().unwrap_or_default().into_bytes(),
+            });
+        }
+        _ => {}
+    }
+    all.extend_from_slice(formats);
+    serve_formats(all, primary)
+}
+
+#[cfg(target_os = "linux")]
+fn serve_formats(formats: Vec<ClipboardFormat>, primary: bool) -> Result<(), String> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        match super::wayland::set_selection(formats.clone(), primary) {
+            Ok(()) => return Ok(()),
+            // 合成器不支持 data-control 时退回 XWayland
+            Err(e) => eprintln!("Wayland data-control copy failed: {}", e),
+        }
+    }
+    super::x11::serve_selection(formats, primary)
+}
+
+/// 按 MIME 类型原样写入一组格式，格式和文件列表都为空时清空剪贴板。
+/// Linux 下提供全部格式，文件列表在 text/uri-list 中；其他平台文件列表单独传入，
+/// 只能还原文件列表、HTML、文本和 PNG 图片
+pub fn write_formats(formats: Vec<ClipboardFormat>, files: Vec<PathBuf>) -> Result<(), String> {
+    if formats.is_empty() && files.is_empty() {
+        return Clipboard::new()
+            .and_then(|mut clipboard| clipboard.clear())
+            .map_err(|e| e.to_string());
+    }
+
+    #[cfg(target_os = "linux")]
+    match serve_formats(formats.clone(), false) {
+        Ok(()) => return Ok(()),
+        Err(e) => eprintln!("Failed to restore all clipboard formats: {}", e),
+    }
+
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+    let find = |prefix: &str| {
+        formats
+            .iter()
+            .find(|f| f.mime_type.starts_with(prefix))
+            .and_then(|f| String::from_utf8(f.data.clone()).ok())
+    };
+    let text = find("text/plain");
+    #[cfg(target_os = "linux")]
+    let files = match find("text/uri-list") {
+        Some(list) => super::files::parse_uri_list(&list),
+        None => files,
+    };
+    if !files.is_empty() {
+        return clipboard.set().file_list(&files).map_err(|e| e.to_string());
+    }
+    if let Some(html) = find("text/html") {
+        return clipboard
+            .set()
+            .html(html.as_str(), text.as_deref())
+            .map_err(|e| e.to_string());
+    }
+    if let Some(text) = text {
+        return clipboard.set_text(text).map_err(|e| e.to_string());
+    }
+    match formats.iter().find(|f| f.mime_type == "image/png") {
+        Some(png) => clipboard
+            .set_image(decode_image(&png.data)?)
+            .map_err(|e| e.to_string()),
+        None => Err("No restorable clipboard format".to_string()),
+    }
+}
+
+/// arboard 只支持文本、HTML、图片和文件列表
+fn write_with_arboard(
+    item: &ClipboardItem,
+    formats: &[ClipboardFormat],
+    image: Option<&[u8]>,
+) -> Result<(), String> {
+    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
+
+    match item.content_type.as_str() {
+        "text" => {
+            let text = item.text_content.as_deref().unwrap_or_default();
+            let html = formats
+                .iter()
+                .find(|f| f.mime_type == "text/html")
+                .and_then(|f| String::from_utf8(f.data.clone()).ok());
+            match html {
+                Some(html) => clipboard
+                    .set()
+                    .html(html.as_str(), Some(text))
+                    .map_err(|e| e.to_string())?,
+                None => clipboard.set_text(text).map_err(|e| e.to_string())?,
+            }
+        }
+        "image" => {
+            let data = image.ok_or("Image file is missing")?;
+            clipboard
+                .set_image(decode_image(data)?)
+                .map_err(|e| e.to_string())?;
+        }
+        "files" => {
+            clipboard
+                .set()
+                .file_list(&file_paths(item))
+                .map_err(|e| e.to_string())?;
+        }
+        _ => return Err("Unsupported content type".to_string()),
+    }
+
+    Ok(())
+}
+
+fn decode_image(data: &[u8]) -> Result<arboard::ImageData<'static>, String> {
+    let rgba = image::load_from_memory(data)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (width, height) = rgba.dimensions();
+    Ok(arboard::ImageData {
+        width: width as usize,
+        height: height as usize,
+        bytes: rgba.into_raw().into(),
+    })
+}
+
+fn file_paths(item: &ClipboardItem) -> Vec<PathBuf> {
+    item.files.iter().map(|f| PathBuf::from(&f.path)).collect()
+}
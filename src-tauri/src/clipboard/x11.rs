@@ -0,0 +1,572 @@
+use super::backend::{is_extra_format, ClipboardBackend};
+use crate::focus;
+use crate::models::{ClipboardFormat, SourceApp};
+use arboard::{Clipboard, ImageData};
+use std::path::PathBuf;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask,
+    GetPropertyReply, PropMode, Property, SelectionNotifyEvent, Window, WindowClass,
+    SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, CURRENT_TIME, NONE};
+
+/// 等待选区所有者转换数据的超时时间
+const CONVERT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// serve_selection 已送达请求方的数据次数，临时粘贴据此得知目标应用已读完
+static SERVED: Mutex<u64> = Mutex::new(0);
+static SERVED_CHANGED: Condvar = Condvar::new();
+
+/// 纯文本在 X11 下的传统目标名，写回时与 text/plain 一并提供
+const TEXT_TARGETS: &[&str] = &["UTF8_STRING", "STRING", "TEXT"];
+
+/// X11 后端：通过 XFixes 订阅 CLIPBOARD 所有者变化，只在真正变化时读取内容
+pub struct X11Backend {
+    conn: RustConnection,
+    clipboard: Clipboard,
+    /// 接收 ConvertSelection 结果的隐藏窗口
+    window: Window,
+    root: Window,
+    atoms: Atoms,
+    first: bool,
+    /// 读取格式期间收到的所有者变化，下次等待时立即返回
+    changed: bool,
+    /// 单个格式的大小上限，INCR 分段传输超过时放弃
+    max_size: usize,
+}
+
+struct Atoms {
+    clipboard: Atom,
+    targets: Atom,
+    incr: Atom,
+    property: Atom,
+}
+
+impl X11Backend {
+    pub fn new() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+
+        // XFixes 2.0 起支持 SelectSelectionInput
+        let version = conn
+            .xfixes_query_version(5, 0)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        if version.major_version < 2 {
+            return Err(format!(
+                "XFixes {}.{} is too old",
+                version.major_version, version.minor_version
+            ));
+        }
+
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms {
+            clipboard: intern(&conn, "CLIPBOARD")?,
+            targets: intern(&conn, "TARGETS")?,
+            incr: intern(&conn, "INCR")?,
+            property: intern(&conn, "SUPER_PASTE_SELECTION")?,
+        };
+        conn.xfixes_select_selection_input(
+            root,
+            atoms.clipboard,
+            SelectionEventMask::SET_SELECTION_OWNER,
+        )
+        .map_err(|e| e.to_string())?
+        .check()
+        .map_err(|e| e.to_string())?;
+        let window = requestor_window(&conn, root)?;
+
+        Ok(Self {
+            conn,
+            clipboard: Clipboard::new().map_err(|e| e.to_string())?,
+            window,
+            root,
+            atoms,
+            first: true,
+            changed: false,
+            max_size: usize::MAX,
+        })
+    }
+
+    /// 请求选区所有者把内容转换为指定目标，写入隐藏窗口的属性后读取
+    fn convert(&mut self, target: Atom) -> Option<Vec<u8>> {
+        self.conn
+            .convert_selection(
+                self.window,
+                self.atoms.clipboard,
+                target,
+                self.atoms.property,
+                CURRENT_TIME,
+            )
+            .ok()?;
+        self.conn.flush().ok()?;
+
+        let deadline = Instant::now() + CONVERT_TIMEOUT;
+        loop {
+            match self.conn.poll_for_event().ok()? {
+                Some(Event::SelectionNotify(event)) if event.requestor == self.window => {
+                    if event.property == NONE {
+                        return None;
+                    }
+                    break;
+                }
+                Some(Event::XfixesSelectionNotify(_)) => self.changed = true,
+                Some(_) => {}
+                None if Instant::now() >= deadline => return None,
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+
+        let reply = self.take_property()?;
+        if reply.type_ != self.atoms.incr {
+            return Some(reply.value);
+        }
+        // 大块数据按 INCR 分段传输，属性中是总长度的下限
+        let size = reply.value32().and_then(|mut v| v.next()).unwrap_or(0) as usize;
+        if size > self.max_size {
+            self.abort_transfer();
+            return None;
+        }
+        self.receive_incr()
+    }
+
+    /// 读取并删除隐藏窗口上的属性，删除后选区所有者才会写入下一段
+    fn take_property(&self) -> Option<GetPropertyReply> {
+        self.conn
+            .get_property(
+                true,
+                self.window,
+                self.atoms.property,
+                AtomEnum::ANY,
+                0,
+                u32::MAX / 4,
+            )
+            .ok()?
+            .reply()
+            .ok()
+    }
+
+    /// 逐段读取 INCR 传输，直到所有者写入空数据。超过大小上限或超时时放弃
+    fn receive_incr(&mut self) -> Option<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut deadline = Instant::now() + CONVERT_TIMEOUT;
+        loop {
+            match self.conn.poll_for_event().ok()? {
+                Some(Event::PropertyNotify(event))
+                    if event.window == self.window
+                        && event.atom == self.atoms.property
+                        && event.state == Property::NEW_VALUE =>
+                {
+                    let Some(chunk) = self.take_property() else {
+                        self.abort_transfer();
+                        return None;
+                    };
+                    if chunk.value.is_empty() {
+                        return Some(data);
+                    }
+                    if data.len() + chunk.value.len() > self.max_size {
+                        self.abort_transfer();
+                        return None;
+                    }
+                    data.extend_from_slice(&chunk.value);
+                    deadline = Instant::now() + CONVERT_TIMEOUT;
+                }
+                Some(Event::XfixesSelectionNotify(_)) => self.changed = true,
+                Some(_) => {}
+                None if Instant::now() >= deadline => {
+                    self.abort_transfer();
+                    return None;
+                }
+                None => thread::sleep(Duration::from_millis(5)),
+            }
+        }
+    }
+
+    /// 换用新的隐藏窗口，所有者继续写入旧窗口时失败并结束这次传输，
+    /// 之后的读取不会收到它剩余的分段
+    fn abort_transfer(&mut self) {
+        let Ok(window) = requestor_window(&self.conn, self.root) else {
+            return;
+        };
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+        self.window = window;
+    }
+
+    /// 当前所有者提供的全部目标及其名称
+    fn targets(&mut self) -> Vec<(Atom, String)> {
+        let Some(data) = self.convert(self.atoms.targets) else {
+            return Vec::new();
+        };
+        data.chunks_exact(4)
+            .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .filter_map(|atom| Some((atom, atom_name(&self.conn, atom)?)))
+            .collect()
+    }
+}
+
+impl ClipboardBackend for X11Backend {
+    fn wait_for_change(&mut self) -> Result<(), String> {
+        // 启动时立即读取一次已有内容
+        if std::mem::take(&mut self.first) || std::mem::take(&mut self.changed) {
+            return Ok(());
+        }
+
+        loop {
+            match self.conn.wait_for_event().map_err(|e| e.to_string())? {
+                Event::XfixesSelectionNotify(_) => return Ok(()),
+                _ => continue,
+            }
+        }
+    }
+
+    fn read_text(&mut self) -> Option<String> {
+        self.clipboard.get_text().ok()
+    }
+
+    fn read_image(&mut self) -> Option<ImageData<'static>> {
+        self.clipboard.get_image().ok()
+    }
+
+    fn set_max_size(&mut self, max_bytes: i64) {
+        self.max_size = max_bytes.max(0) as usize;
+    }
+
+    fn read_formats(&mut self) -> Vec<ClipboardFormat> {
+        let mut formats = Vec::new();
+        for (target, name) in self.targets() {
+            if !is_extra_format(&name) {
+                continue;
+            }
+            if let Some(data) = self.convert(target) {
+                formats.push(ClipboardFormat {
+                    mime_type: name,
+                    data,
+                });
+            }
+        }
+        formats
+    }
+
+    fn read_files(&mut self) -> Vec<PathBuf> {
+        self.clipboard.get().file_list().unwrap_or_default()
+    }
+
+    fn mime_types(&mut self) -> Vec<String> {
+        self.targets().into_iter().map(|(_, name)| name).collect()
+    }
+
+    fn read_data(&mut self, mime_type: &str) -> Option<Vec<u8>> {
+        let (target, _) = self
+            .targets()
+            .into_iter()
+            .find(|(_, name)| name == mime_type)?;
+        self.convert(target)
+    }
+
+    fn source_app(&mut self) -> Option<SourceApp> {
+        // 选区所有者常是程序的隐藏窗口，没有 WM_CLASS/_NET_WM_PID 时退回激活窗口
+        let owner = self
+            .conn
+            .get_selection_owner(self.atoms.clipboard)
+            .ok()?
+            .reply()
+            .ok()?
+            .owner;
+        if owner != NONE {
+            if let Some(app) = focus::x11_window_app(&self.conn, owner) {
+                return Some(app);
+            }
+        }
+        let active = focus::x11_active_window(&self.conn, self.root)?;
+        focus::x11_window_app(&self.conn, active)
+    }
+}
+
+/// 向一个请求方分段发送的大块数据，请求方每删除一次属性就写入下一段
+struct IncrTransfer {
+    requestor: Window,
+    property: Atom,
+    target: Atom,
+    index: usize,
+    offset: usize,
+}
+
+/// 已送达的数据次数，TARGETS 查询不计入，分段传输在最后一段写完时计入
+pub fn served_requests() -> u64 {
+    *SERVED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 等待 since 之后有数据送达，超时返回 false
+pub fn wait_for_request(since: u64, timeout: Duration) -> bool {
+    let served = SERVED.lock().unwrap_or_else(|e| e.into_inner());
+    let (served, _) = SERVED_CHANGED
+        .wait_timeout_while(served, timeout, |served| *served == since)
+        .unwrap_or_else(|e| e.into_inner());
+    *served != since
+}
+
+fn record_served() {
+    *SERVED.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+    SERVED_CHANGED.notify_all();
+}
+
+/// 成为 CLIPBOARD（primary 为 true 时还有 PRIMARY）的所有者并在后台线程响应粘贴请求，
+/// 直到其他程序接管全部选区且分段传输都已完成
+pub fn serve_selection(formats: Vec<ClipboardFormat>, primary: bool) -> Result<(), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = create_window(&conn, root)?;
+    let mut selections = vec![intern(&conn, "CLIPBOARD")?];
+    if primary {
+        selections.push(AtomEnum::PRIMARY.into());
+    }
+    let targets_atom = intern(&conn, "TARGETS")?;
+    let incr_atom = intern(&conn, "INCR")?;
+
+    // 目标 atom 到格式下标的映射，纯文本同时以传统目标名提供
+    let mut targets: Vec<(Atom, usize)> = Vec::new();
+    for (index, format) in formats.iter().enumerate() {
+        targets.push((intern(&conn, &format.mime_type)?, index));
+        if format.mime_type.starts_with("text/plain") {
+            for name in TEXT_TARGETS {
+                targets.push((intern(&conn, name)?, index));
+            }
+        }
+    }
+
+    for selection in &selections {
+        conn.set_selection_owner(window, *selection, CURRENT_TIME)
+            .map_err(|e| e.to_string())?;
+        let owner = conn
+            .get_selection_owner(*selection)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .owner;
+        if owner != window {
+            return Err("Failed to take ownership of the clipboard".into());
+        }
+    }
+
+    // 超过单个请求上限的数据按 INCR 协议分段传输，预留请求头的空间
+    let max_len = conn.maximum_request_bytes().saturating_sub(64);
+
+    thread::spawn(move || {
+        let mut transfers: Vec<IncrTransfer> = Vec::new();
+        while let Ok(event) = conn.wait_for_event() {
+            match event {
+                Event::SelectionRequest(request) => {
+                    let property = if request.property == NONE {
+                        request.target
+                    } else {
+                        request.property
+                    };
+                    let served = if request.target == targets_atom {
+                        let mut list = vec![targets_atom];
+                        list.extend(targets.iter().map(|(atom, _)| *atom));
+                        conn.change_property32(
+                            PropMode::REPLACE,
+                            request.requestor,
+                            property,
+                            AtomEnum::ATOM,
+                            &list,
+                        )
+                        .is_ok()
+                    } else if let Some((_, index)) =
+                        targets.iter().find(|(atom, _)| *atom == request.target)
+                    {
+                        let data = &formats[*index].data;
+                        if data.len() <= max_len {
+                            let written = conn
+                                .change_property8(
+                                    PropMode::REPLACE,
+                                    request.requestor,
+                                    property,
+                                    request.target,
+                                    data,
+                                )
+                                .is_ok();
+                            if written {
+                                record_served();
+                            }
+                            written
+                        } else {
+                            // 先告知总长度，随后等请求方删除属性时逐段写入
+                            let started = conn
+                                .change_window_attributes(
+                                    request.requestor,
+                                    &ChangeWindowAttributesAux::new()
+                                        .event_mask(EventMask::PROPERTY_CHANGE),
+                                )
+                                .is_ok()
+                                && conn
+                                    .change_property32(
+                                        PropMode::REPLACE,
+                                        request.requestor,
+                                        property,
+                                        incr_atom,
+                                        &[data.len() as u32],
+                                    )
+                                    .is_ok();
+                            if started {
+                                transfers.retain(|t| {
+                                    (t.requestor, t.property) != (request.requestor, property)
+                                });
+                                transfers.push(IncrTransfer {
+                                    requestor: request.requestor,
+                                    property,
+                                    target: request.target,
+                                    index: *index,
+                                    offset: 0,
+                                });
+                            }
+                            started
+                        }
+                    } else {
+                        false
+                    };
+
+                    let notify = SelectionNotifyEvent {
+                        response_type: SELECTION_NOTIFY_EVENT,
+                        sequence: 0,
+                        time: request.time,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property: if served { property } else { NONE },
+                    };
+                    let _ = conn.send_event(false, request.requestor, EventMask::NO_EVENT, notify);
+                    let _ = conn.flush();
+                }
+                Event::PropertyNotify(event) if event.state == Property::DELETE => {
+                    let Some(position) = transfers
+                        .iter()
+                        .position(|t| t.requestor == event.window && t.property == event.atom)
+                    else {
+                        continue;
+                    };
+                    let transfer = &mut transfers[position];
+                    let data = &formats[transfer.index].data;
+                    let end = (transfer.offset + max_len).min(data.len());
+                    // 最后写入一段空数据表示传输结束
+                    let chunk = &data[transfer.offset..end];
+                    let written = conn
+                        .change_property8(
+                            PropMode::REPLACE,
+                            transfer.requestor,
+                            transfer.property,
+                            transfer.target,
+                            chunk,
+                        )
+                        .is_ok();
+                    transfer.offset = end;
+                    if written && chunk.is_empty() {
+                        record_served();
+                    }
+                    if !written || chunk.is_empty() {
+                        transfers.remove(position);
+                    }
+                    let _ = conn.flush();
+                    if selections.is_empty() && transfers.is_empty() {
+                        break;
+                    }
+                }
+                Event::SelectionClear(event) => {
+                    selections.retain(|selection| *selection != event.selection);
+                    if selections.is_empty() && transfers.is_empty() {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    Ok(())
+}
+
+fn create_window(conn: &RustConnection, root: Window) -> Result<Window, String> {
+    let window = conn.generate_id().map_err(|e| e.to_string())?;
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(window)
+}
+
+/// 接收转换结果的隐藏窗口，监听属性变化以支持 INCR 分段传输
+fn requestor_window(conn: &RustConnection, root: Window) -> Result<Window, String> {
+    let window = create_window(conn, root)?;
+    conn.change_window_attributes(
+        window,
+        &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(window)
+}
+
+fn intern(conn: &RustConnection, name: &str) -> Result<Atom, String> {
+    Ok(conn
+        .intern_atom(false, name.as_bytes())
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?
+        .atom)
+}
+
+fn atom_name(conn: &RustConnection, atom: Atom) -> Option<String> {
+    let reply = conn.get_atom_name(atom).ok()?.reply().ok()?;
+    String::from_utf8(reply.name).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 需要 X 服务器，例如：`Xvfb :99 & DISPLAY=:99 cargo test -- --ignored`
+    #[test]
+    #[ignore = "requires an X server with XFixes"]
+    fn large_formats_are_read_incrementally_up_to_the_limit() {
+        let mut backend = X11Backend::new().expect("connect to X server");
+        let len = backend.conn.maximum_request_bytes() * 3;
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        serve_selection(
+            vec![ClipboardFormat {
+                mime_type: "text/html".to_string(),
+                data: data.clone(),
+            }],
+            false,
+        )
+        .unwrap();
+
+        backend.set_max_size(len as i64);
+        let served = served_requests();
+        assert_eq!(backend.read_data("text/html"), Some(data));
+        // 分段传输写完最后一段才算送达
+        assert!(wait_for_request(served, CONVERT_TIMEOUT));
+        // 超过上限时放弃读取，之后的读取不受中断的传输影响
+        backend.set_max_size(len as i64 - 1);
+        assert_eq!(backend.read_data("text/html"), None);
+        backend.set_max_size(len as i64);
+        assert_eq!(backend.read_data("text/html").map(|d| d.len()), Some(len));
+    }
+}
@@ -1,20 +1,69 @@
-use tauri::Manager;
-use crate::models::Settings;
+use tauri::{Emitter, Manager, State};
+use crate::clipboard::{self, OwnWrites};
+use crate::crypto::{EncryptionState, EncryptionStatus};
+use crate::maintenance;
+use crate::models::{AppRule, ClipboardItem, MaintenanceReport, PasteChord, Settings};
 use crate::focus;
-use std::fs;
-use std::process::Command;
+use crate::input;
+use crate::retention;
+use crate::rules::AppRulesState;
+use crate::settings::{self, SettingsError, SettingsState};
+use crate::shortcut::{ShortcutManager, ShortcutStatus};
+use crate::storage::{self, HistoryStore, DEFAULT_LIST_LIMIT};
+use std::path::Path;
 
 #[tauri::command]
-pub async fn get_settings() -> Result<Settings, String> {
-    Ok(Settings::default())
+pub async fn get_settings(state: State<'_, SettingsState>) -> Result<Settings, String> {
+    Ok(state.get())
 }
 
+/// 校验并持久化设置，随后立即应用到快捷键和保留策略
 #[tauri::command]
-pub async fn save_settings(settings: Settings) -> Result<(), String> {
-    println!("Saving settings: {:?}", settings);
+pub async fn save_settings(
+    app: tauri::AppHandle,
+    store: State<'_, HistoryStore>,
+    state: State<'_, SettingsState>,
+    shortcuts: State<'_, ShortcutManager>,
+    settings: Settings,
+) -> Result<(), SettingsError> {
+    settings
+        .validate()
+        .map_err(|message| SettingsError::Invalid { message })?;
+    let previous = state.get();
+    let shortcut_changed = settings.shortcut != previous.shortcut;
+
+    if shortcut_changed {
+        shortcuts
+            .rebind(&app, &settings.shortcut)
+            .map_err(|error| SettingsError::Shortcut { error })?;
+    }
+
+    if let Err(e) = store.save_settings(&settings::to_rows(&settings)) {
+        // 持久化失败时恢复原快捷键
+        if shortcut_changed {
+            let _ = shortcuts.rebind(&app, &previous.shortcut);
+        }
+        return Err(SettingsError::Storage {
+            message: e.to_string(),
+        });
+    }
+
+    state.set(settings.clone());
+
+    // 新的保留策略和图片空间上限立即生效
+    retention::run(&app);
+
+    let _ = app.emit("settings-changed", &settings);
     Ok(())
 }
 
+#[tauri::command]
+pub async fn get_shortcut_status(
+    shortcuts: State<'_, ShortcutManager>,
+) -> Result<ShortcutStatus, String> {
+    Ok(shortcuts.status())
+}
+
 #[tauri::command]
 pub async fn hide_window(app: tauri::AppHandle) -> Result<(), String> {
     if let Some(window) = app.get_webview_window("main") {
@@ -35,30 +84,44 @@ pub async fn show_window(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// 将历史条目连同保存的全部格式写回剪贴板，监听线程不会把这次写入当作新内容
 #[tauri::command]
-pub async fn copy_to_clipboard(content: String, content_type: String) -> Result<(), String> {
-    use arboard::Clipboard;
-    let mut clipboard = Clipboard::new().map_err(|e| e.to_string())?;
-    
-    match content_type.as_str() {
-        "text" => {
-            clipboard.set_text(&content).map_err(|e| e.to_string())?;
-        }
-        "image" => {
-            // Load image from path and set to clipboard
-            let img = image::open(&content).map_err(|e| e.to_string())?;
-            let rgba = img.to_rgba8();
-            let (width, height) = rgba.dimensions();
-            let img_data = arboard::ImageData {
-                width: width as usize,
-                height: height as usize,
-                bytes: rgba.into_raw().into(),
-            };
-            clipboard.set_image(img_data).map_err(|e| e.to_string())?;
+pub async fn copy_to_clipboard(
+    app: tauri::AppHandle,
+    store: State<'_, HistoryStore>,
+    settings: State<'_, SettingsState>,
+    own_writes: State<'_, OwnWrites>,
+    id: i64,
+) -> Result<(), String> {
+    write_history_item(&app, &store, &settings.get(), &own_writes, id)
+}
+
+fn write_history_item(
+    app: &tauri::AppHandle,
+    store: &HistoryStore,
+    settings: &Settings,
+    own_writes: &OwnWrites,
+    id: i64,
+) -> Result<(), String> {
+    let item = store
+        .get(id)
+        .map_err(|e| e.to_string())?
+        .ok_or("Item not found")?;
+    let formats = store.formats(id).map_err(|e| e.to_string())?;
+    let image = match item.image_path.as_deref() {
+        Some(path) if item.content_type == "image" => Some(store.read_image(Path::new(path))?),
+        _ => None,
+    };
+    // 目标应用用中键粘贴时需要同时写入 PRIMARY 选区
+    let chord = input::chord_for(&settings.paste_chords, focus::paste_target().as_ref());
+    let primary = chord == PasteChord::MiddleClick;
+    clipboard::write_item(&item, formats, image, primary, own_writes)?;
+
+    if settings.move_to_top_on_paste && store.touch(id).map_err(|e| e.to_string())? {
+        if let Some(item) = store.get(id).map_err(|e| e.to_string())? {
+            let _ = app.emit("clipboard-changed", storage::mask_sensitive(item));
         }
-        _ => return Err("Unsupported content type".to_string()),
     }
-    
     Ok(())
 }
 
@@ -79,69 +142,257 @@ fn position_window_bottom(app: &tauri::AppHandle) {
 }
 
 #[tauri::command]
-pub async fn delete_image_files(
-    image_path: Option<String>,
-    thumbnail_path: Option<String>,
-) -> Result<(), String> {
-    // 删除原图
-    if let Some(path) = image_path {
-        if !path.is_empty() {
-            let _ = fs::remove_file(&path);
-        }
+pub async fn list_items(
+    store: State<'_, HistoryStore>,
+    limit: Option<u32>,
+    app: Option<String>,
+) -> Result<Vec<ClipboardItem>, String> {
+    store
+        .list(limit.unwrap_or(DEFAULT_LIST_LIMIT), app.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn search_items(
+    store: State<'_, HistoryStore>,
+    query: String,
+    limit: Option<u32>,
+    app: Option<String>,
+) -> Result<Vec<ClipboardItem>, String> {
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    if query.trim().is_empty() {
+        return store.list(limit, app.as_deref()).map_err(|e| e.to_string());
     }
+    store
+        .search(&query, limit, app.as_deref())
+        .map_err(|e| e.to_string())
+}
 
-    // 删除缩略图
-    if let Some(path) = thumbnail_path {
-        if !path.is_empty() {
-            let _ = fs::remove_file(&path);
-        }
+#[tauri::command]
+pub async fn get_item(
+    store: State<'_, HistoryStore>,
+    id: i64,
+) -> Result<Option<ClipboardItem>, String> {
+    store.get(id).map_err(|e| e.to_string())
+}
+
+/// 删除记录及其图片文件
+#[tauri::command]
+pub async fn delete_item(store: State<'_, HistoryStore>, id: i64) -> Result<(), String> {
+    if let Some(item) = store.delete(id).map_err(|e| e.to_string())? {
+        store.delete_files(item.image_path.as_deref(), item.thumbnail_path.as_deref());
     }
+    Ok(())
+}
+
+/// 清理孤立的图片文件并修复缩略图和原图丢失的记录
+#[tauri::command]
+pub async fn run_maintenance(
+    app: tauri::AppHandle,
+    store: State<'_, HistoryStore>,
+    encryption: State<'_, EncryptionState>,
+) -> Result<MaintenanceReport, String> {
+    // 未解锁时无法读取加密的原图来生成缩略图
+    if encryption.is_locked(&store) {
+        return Err("请先解锁历史记录".to_string());
+    }
+    let images_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("images");
+    let report = maintenance::run(&store, &images_dir)?;
+    let _ = app.emit("history-repaired", &report);
+    Ok(report)
+}
 
+/// 固定的条目不会因图片空间超限被清理
+#[tauri::command]
+pub async fn set_pinned(
+    store: State<'_, HistoryStore>,
+    id: i64,
+    pinned: bool,
+) -> Result<(), String> {
+    store.set_pinned(id, pinned).map_err(|e| e.to_string())?;
     Ok(())
 }
 
-/// 模拟粘贴操作 (Cmd+V / Ctrl+V)
+/// 刷新条目时间，使其排到最前
 #[tauri::command]
-pub async fn paste() -> Result<(), String> {
-    // 短暂延迟确保焦点已恢复到目标应用
-    std::thread::sleep(std::time::Duration::from_millis(100));
+pub async fn touch_item(store: State<'_, HistoryStore>, id: i64) -> Result<(), String> {
+    store.touch(id).map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        // 使用 AppleScript 模拟 Cmd+V
-        let script = r#"tell application "System Events" to keystroke "v" using command down"#;
-        let output = Command::new("osascript")
-            .args(["-e", script])
-            .output()
-            .map_err(|e| e.to_string())?;
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
+#[tauri::command]
+pub async fn get_encryption_status(
+    store: State<'_, HistoryStore>,
+    encryption: State<'_, EncryptionState>,
+) -> Result<EncryptionStatus, String> {
+    Ok(encryption.status(&store))
+}
+
+/// 用口令解锁历史记录，并写入锁定期间暂存的捕获
+#[tauri::command]
+pub async fn unlock_history(
+    app: tauri::AppHandle,
+    store: State<'_, HistoryStore>,
+    encryption: State<'_, EncryptionState>,
+    passphrase: String,
+) -> Result<(), String> {
+    encryption.unlock(&store, &passphrase)?;
+    for (payload, image) in encryption.take_pending() {
+        clipboard::store_capture(&app, payload, image);
     }
+    let _ = app.emit("history-unlocked", ());
+    Ok(())
+}
 
-    #[cfg(target_os = "windows")]
-    {
-        // Windows: 使用 PowerShell 模拟 Ctrl+V
-        let output = Command::new("powershell")
-            .args(["-Command", r#"Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SendKeys]::SendWait('^v')"#])
-            .output()
-            .map_err(|e| e.to_string())?;
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
-        }
+/// 开启静态加密，不提供口令时使用密钥文件
+#[tauri::command]
+pub async fn enable_encryption(
+    store: State<'_, HistoryStore>,
+    encryption: State<'_, EncryptionState>,
+    passphrase: Option<String>,
+) -> Result<EncryptionStatus, String> {
+    encryption.enable(&store, passphrase.as_deref())?;
+    Ok(encryption.status(&store))
+}
+
+#[tauri::command]
+pub async fn disable_encryption(
+    store: State<'_, HistoryStore>,
+    encryption: State<'_, EncryptionState>,
+) -> Result<EncryptionStatus, String> {
+    encryption.disable(&store)?;
+    Ok(encryption.status(&store))
+}
+
+#[tauri::command]
+pub async fn list_app_rules(rules: State<'_, AppRulesState>) -> Result<Vec<AppRule>, String> {
+    Ok(rules.get())
+}
+
+/// 校验并保存应用规则，没有 id 时新建，保存后立即对新捕获生效
+#[tauri::command]
+pub async fn save_app_rule(
+    store: State<'_, HistoryStore>,
+    rules: State<'_, AppRulesState>,
+    rule: AppRule,
+) -> Result<AppRule, String> {
+    rule.validate()?;
+    let saved = store.save_rule(&rule).map_err(|e| e.to_string())?;
+    rules.set(store.load_rules().map_err(|e| e.to_string())?);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn delete_app_rule(
+    store: State<'_, HistoryStore>,
+    rules: State<'_, AppRulesState>,
+    id: i64,
+) -> Result<(), String> {
+    store.delete_rule(id).map_err(|e| e.to_string())?;
+    rules.set(store.load_rules().map_err(|e| e.to_string())?);
+    Ok(())
+}
+
+/// 临时粘贴时暂停监听的最长时间，正常情况下恢复后即提前结束
+const TRANSIENT_PAUSE: std::time::Duration = std::time::Duration::from_secs(10);
+/// 粘贴按键发出后等待目标应用读取剪贴板的最长时间。X11 下目标应用读完即恢复；
+/// Wayland 和其他平台无法得知何时读取，只能等满这段时间，应用响应更慢时
+/// 可能粘贴出恢复后的内容
+const RESTORE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+/// 恢复后继续暂停监听的时间，等待迟到的变化通知
+const RESTORE_GRACE: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// 记下当前剪贴板，写入条目并粘贴，再写回原内容。期间监听线程暂停，
+/// 中间状态不会进入历史
+fn paste_transient(
+    app: &tauri::AppHandle,
+    settings: &Settings,
+    id: i64,
+    chord: PasteChord,
+) -> Result<(), String> {
+    let own_writes = app.state::<OwnWrites>();
+    own_writes.pause(TRANSIENT_PAUSE);
+    let result = clipboard::ClipboardSnapshot::take()
+        .map_err(|e| format!("无法读取当前剪贴板：{}", e))
+        .and_then(|snapshot| {
+            let store = app.state::<HistoryStore>();
+            let served = clipboard::served_requests();
+            let pasted = write_history_item(app, &store, settings, &own_writes, id)
+                .and_then(|_| input::send_paste(chord));
+            clipboard::wait_until_read(served, RESTORE_DELAY);
+            let restored = snapshot
+                .restore()
+                .map_err(|e| format!("无法恢复粘贴前的剪贴板：{}", e));
+            pasted.and(restored)
+        });
+    own_writes.pause(RESTORE_GRACE);
+    result
+}
+
+/// 模拟粘贴操作，按唤出面板前的应用选择快捷键 (Cmd+V / Ctrl+V / 终端的粘贴方式)。
+/// 传入条目 id 时先把它写入剪贴板，开启临时粘贴时粘贴后再恢复原来的剪贴板内容。
+/// 中键粘贴读取的是 PRIMARY 选区，无法恢复，不使用临时粘贴。
+/// type_out 为 true 或目标应用配置为逐字输入时，把条目 id 的文本模拟为按键输入。
+/// 只是目标应用配置为逐字输入时，非文本条目仍按 Ctrl+V 粘贴
+#[tauri::command]
+pub async fn paste(
+    app: tauri::AppHandle,
+    id: Option<i64>,
+    type_out: Option<bool>,
+) -> Result<(), String> {
+    // 等待焦点、模拟按键和等待目标应用读取都会阻塞，放到阻塞线程里。
+    // 逐字输入期间的中止快捷键由全局快捷键回调处理
+    tauri::async_runtime::spawn_blocking(move || paste_blocking(&app, id, type_out))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn paste_blocking(
+    app: &tauri::AppHandle,
+    id: Option<i64>,
+    type_out: Option<bool>,
+) -> Result<(), String> {
+    // 短暂延迟确保焦点已恢复到目标应用。X11 下恢复焦点时已等待窗口管理器确认
+    if !cfg!(target_os = "linux") || focus::is_wayland_session() {
+        std::thread::sleep(std::time::Duration::from_millis(100));
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        // Linux: 使用 xdotool 模拟 Ctrl+V (需要安装 xdotool)
-        let output = Command::new("xdotool")
-            .args(["key", "ctrl+v"])
-            .output()
-            .map_err(|e| format!("xdotool not found. Please install it: sudo apt install xdotool. Error: {}", e))?;
-        if !output.status.success() {
-            return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    let store = app.state::<HistoryStore>();
+    let settings = app.state::<SettingsState>().get();
+    let mut chord = match type_out {
+        Some(true) => PasteChord::TypeText,
+        _ => input::chord_for(&settings.paste_chords, focus::paste_target().as_ref()),
+    };
+    let item = match id {
+        Some(id) if chord == PasteChord::TypeText => store.get(id).map_err(|e| e.to_string())?,
+        _ => None,
+    };
+    let text = item
+        .filter(|item| item.content_type == "text")
+        .and_then(|item| item.text_content);
+    if chord == PasteChord::TypeText && text.is_none() {
+        if type_out == Some(true) {
+            return Err("只有文本条目可以逐字输入".to_string());
         }
+        // 目标应用配置为逐字输入，但图片、文件条目无法输入，仍按普通方式粘贴
+        chord = PasteChord::CtrlV;
     }
+    let Some(text) = text else {
+        let Some(id) = id else {
+            return input::send_paste(chord);
+        };
+        if !settings.restore_clipboard_after_paste || chord == PasteChord::MiddleClick {
+            write_history_item(app, &store, &settings, &app.state::<OwnWrites>(), id)?;
+            return input::send_paste(chord);
+        }
+        return paste_transient(app, &settings, id, chord);
+    };
 
-    Ok(())
+    let delay = std::time::Duration::from_millis(settings.type_delay_ms as u64);
+    app.state::<input::TypingState>()
+        .run(app, &text, delay, &settings.type_abort_shortcut)
 }
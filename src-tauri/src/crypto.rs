@@ -0,0 +1,678 @@
+use crate::clipboard::{self, PendingImage};
+use crate::models::ClipboardPayload;
+use crate::storage::HistoryStore;
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// 加密文本的前缀，读取时据此区分明文和密文，迁移中途中断也能正确读取
+const TEXT_PREFIX: &str = "enc1:";
+
+/// 加密文件和二进制数据的文件头
+const BLOB_MAGIC: &[u8] = b"SPENC1\0";
+
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+/// 配置目录下的加密配置文件和密钥文件
+const CONFIG_FILE: &str = "encryption.json";
+const KEY_FILE: &str = "history.key";
+
+/// 用来校验口令是否正确的固定明文
+const VERIFIER: &[u8] = b"super-paste";
+
+/// 锁定期间最多暂存的捕获条数，超出时丢弃最早的
+const MAX_PENDING: usize = 100;
+/// 暂存的图片保留在内存中，单独限制数量
+const MAX_PENDING_IMAGES: usize = 10;
+
+/// XChaCha20-Poly1305 加密，每次加密使用随机 nonce 并写在密文之前
+pub struct Cipher {
+    aead: XChaCha20Poly1305,
+    /// 内容 hash 的 HMAC，密钥由加密密钥派生
+    mac: Hmac<Sha256>,
+}
+
+impl Cipher {
+    pub fn from_key(key: &[u8; KEY_LEN]) -> Self {
+        let mut derive =
+            <Hmac<Sha256> as KeyInit>::new_from_slice(key).expect("HMAC accepts any key length");
+        derive.update(b"super-paste content hash");
+        Self {
+            aead: XChaCha20Poly1305::new(Key::from_slice(key)),
+            mac: <Hmac<Sha256> as KeyInit>::new_from_slice(&derive.finalize().into_bytes())
+                .expect("HMAC accepts any key length"),
+        }
+    }
+
+    /// 带密钥的内容 hash，没有密钥时无法由内容算出，不能用来穷举短密码等内容
+    pub fn keyed_hash(&self, hash: &str) -> String {
+        let mut mac = self.mac.clone();
+        mac.update(hash.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// 用 Argon2id 从口令派生密钥
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Result<Self, String> {
+        let mut key = [0u8; KEY_LEN];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| e.to_string())?;
+        Ok(Self::from_key(&key))
+    }
+
+    fn seal(&self, data: &[u8]) -> Vec<u8> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .aead
+            .encrypt(&nonce, data)
+            .expect("encryption with a valid key cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        sealed
+    }
+
+    fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.aead
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt data, wrong key or corrupted data".to_string())
+    }
+
+    pub fn encrypt_text(&self, text: &str) -> String {
+        format!(
+            "{}{}",
+            TEXT_PREFIX,
+            STANDARD.encode(self.seal(text.as_bytes()))
+        )
+    }
+
+    pub fn encrypt_blob(&self, data: &[u8]) -> Vec<u8> {
+        let mut blob = BLOB_MAGIC.to_vec();
+        blob.extend_from_slice(&self.seal(data));
+        blob
+    }
+}
+
+/// 解密数据库中的文本，明文原样返回；密文在未解锁或解密失败时返回 None
+pub fn decrypt_text(cipher: Option<&Cipher>, value: String) -> Option<String> {
+    let Some(encoded) = value.strip_prefix(TEXT_PREFIX) else {
+        return Some(value);
+    };
+    let sealed = STANDARD.decode(encoded).ok()?;
+    String::from_utf8(cipher?.open(&sealed).ok()?).ok()
+}
+
+/// 解密二进制数据，没有文件头的按明文返回
+pub fn decrypt_blob(cipher: Option<&Cipher>, data: Vec<u8>) -> Result<Vec<u8>, String> {
+    let Some(sealed) = data.strip_prefix(BLOB_MAGIC) else {
+        return Ok(data);
+    };
+    cipher.ok_or("History is locked")?.open(sealed)
+}
+
+pub fn is_encrypted_blob(data: &[u8]) -> bool {
+    data.starts_with(BLOB_MAGIC)
+}
+
+/// 数据库和文件名中使用的内容 hash，开启加密时为带密钥的 hash
+pub fn content_key(cipher: Option<&Cipher>, hash: &str) -> String {
+    match cipher {
+        Some(cipher) => cipher.keyed_hash(hash),
+        None => hash.to_string(),
+    }
+}
+
+/// 读取图片文件，加密过的文件自动解密
+pub fn read_file(path: &Path, cipher: Option<&Cipher>) -> Result<Vec<u8>, String> {
+    decrypt_blob(cipher, fs::read(path).map_err(|e| e.to_string())?)
+}
+
+/// 用新密钥把 src 重写到 dest，两者可以是同一文件。new 为 None 时写回明文
+pub fn rewrite_file(
+    src: &Path,
+    dest: &Path,
+    old: Option<&Cipher>,
+    new: Option<&Cipher>,
+) -> Result<(), String> {
+    let data = fs::read(src).map_err(|e| e.to_string())?;
+    // 已经是明文或已经加密过的文件不再重复处理
+    let unchanged = (new.is_none() && !is_encrypted_blob(&data))
+        || (old.is_none() && new.is_some() && is_encrypted_blob(&data));
+    if unchanged && src == dest {
+        return Ok(());
+    }
+    let output = match new {
+        _ if unchanged => data,
+        Some(cipher) => cipher.encrypt_blob(&decrypt_blob(old, data)?),
+        None => decrypt_blob(old, data)?,
+    };
+    // 先写临时文件再替换，避免中断时留下半个文件
+    let tmp = clipboard::temp_path(dest);
+    fs::write(&tmp, output).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, dest).map_err(|e| e.to_string())
+}
+
+/// 密钥来源，保存在 encryption.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+enum KeySource {
+    /// 口令派生，保存盐和用于校验口令的密文
+    Passphrase { salt: String, verifier: String },
+    /// 随机密钥保存在仅当前用户可读的 history.key
+    KeyFile,
+}
+
+/// encryption.json 的内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EncryptionConfig {
+    #[serde(flatten)]
+    source: KeySource,
+    /// 开启加密时先记下密钥来源再加密已有记录，全部完成后才清除该标记
+    #[serde(default)]
+    incomplete: bool,
+}
+
+/// 前端展示用的加密状态
+#[derive(Debug, Serialize)]
+pub struct EncryptionStatus {
+    pub enabled: bool,
+    pub mode: Option<&'static str>,
+    pub locked: bool,
+    /// 上次开启加密中途失败，部分已有记录仍是明文
+    pub incomplete: bool,
+}
+
+/// 静态加密的配置和锁定状态，密钥本身交给 HistoryStore 使用
+pub struct EncryptionState {
+    config_dir: PathBuf,
+    config: Mutex<Option<EncryptionConfig>>,
+    /// 锁定期间捕获的内容，解锁后再加密入库。图片也等到那时才写出，
+    /// 磁盘上不会留下明文文件
+    pending: Mutex<Vec<(ClipboardPayload, Option<PendingImage>)>>,
+}
+
+impl EncryptionState {
+    /// 读取加密配置，密钥文件模式直接解锁，口令模式等待首次打开面板时解锁
+    pub fn load(config_dir: &Path, store: &HistoryStore) -> Self {
+        let config = match fs::read_to_string(config_dir.join(CONFIG_FILE)) {
+            Ok(json) => match serde_json::from_str::<EncryptionConfig>(&json) {
+                Ok(config) => Some(config),
+                Err(e) => {
+                    eprintln!("Invalid encryption config: {}", e);
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+        let state = Self {
+            config_dir: config_dir.to_path_buf(),
+            config: Mutex::new(config.clone()),
+            pending: Mutex::new(Vec::new()),
+        };
+        if let Some(KeySource::KeyFile) = config.map(|c| c.source) {
+            match state.read_key_file() {
+                Ok(cipher) => store.set_cipher(Some(Arc::new(cipher))),
+                Err(e) => eprintln!("Failed to read history key file: {}", e),
+            }
+        }
+        state
+    }
+
+    fn config(&self) -> Option<EncryptionConfig> {
+        self.config
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn source(&self) -> Option<KeySource> {
+        self.config().map(|config| config.source)
+    }
+
+    /// 先写入配置文件再更新内存状态，config 为 None 时删除配置文件
+    fn save(&self, config: Option<EncryptionConfig>) -> Result<(), String> {
+        let path = self.config_dir.join(CONFIG_FILE);
+        match &config {
+            Some(config) => {
+                let json = serde_json::to_string(config).map_err(|e| e.to_string())?;
+                write_private(&path, json.as_bytes())?;
+            }
+            None => fs::remove_file(&path).map_err(|e| e.to_string())?,
+        }
+        *self.config.lock().unwrap_or_else(|e| e.into_inner()) = config;
+        Ok(())
+    }
+
+    pub fn status(&self, store: &HistoryStore) -> EncryptionStatus {
+        let source = self.source();
+        EncryptionStatus {
+            enabled: source.is_some(),
+            mode: source.as_ref().map(|s| match s {
+                KeySource::Passphrase { .. } => "passphrase",
+                KeySource::KeyFile => "key_file",
+            }),
+            locked: source.is_some() && !store.has_cipher(),
+            incomplete: self.config().is_some_and(|config| config.incomplete),
+        }
+    }
+
+    pub fn is_locked(&self, store: &HistoryStore) -> bool {
+        self.status(store).locked
+    }
+
+    /// 校验口令并解锁历史记录
+    pub fn unlock(&self, store: &HistoryStore, passphrase: &str) -> Result<(), String> {
+        let cipher = match self.source() {
+            Some(KeySource::Passphrase { salt, verifier }) => {
+                let salt = hex::decode(salt).map_err(|e| e.to_string())?;
+                let cipher = Cipher::from_passphrase(passphrase, &salt)?;
+                let sealed = STANDARD.decode(verifier).map_err(|e| e.to_string())?;
+                if cipher.open(&sealed).ok().as_deref() != Some(VERIFIER) {
+                    return Err("口令不正确".to_string());
+                }
+                cipher
+            }
+            Some(KeySource::KeyFile) => self.read_key_file()?,
+            None => return Ok(()),
+        };
+        store.set_cipher(Some(Arc::new(cipher)));
+        Ok(())
+    }
+
+    /// 开启加密并加密已有记录。提供口令时由口令派生密钥，否则生成密钥文件。
+    /// 密钥来源在加密任何数据之前写入配置，中途失败或退出后用同一密钥继续
+    pub fn enable(&self, store: &HistoryStore, passphrase: Option<&str>) -> Result<(), String> {
+        let (source, cipher) = match self.config() {
+            Some(config) if !config.incomplete => return Err("加密已开启".to_string()),
+            Some(config) => {
+                let cipher = store.cipher().ok_or("请先解锁历史记录")?;
+                (config.source, cipher)
+            }
+            None => {
+                let (source, cipher) = self.create_key(passphrase)?;
+                let cipher = Arc::new(cipher);
+                self.save(Some(EncryptionConfig {
+                    source: source.clone(),
+                    incomplete: true,
+                }))?;
+                // 从这里起新捕获的内容也用新密钥加密，已改写的文件同样可以读取
+                store.set_cipher(Some(cipher.clone()));
+                (source, cipher)
+            }
+        };
+
+        store.reencrypt(Some(cipher))?;
+        self.save(Some(EncryptionConfig {
+            source,
+            incomplete: false,
+        }))
+    }
+
+    /// 生成新的密钥来源。已有密钥文件时沿用，不覆盖可能已用它加密过的数据
+    fn create_key(&self, passphrase: Option<&str>) -> Result<(KeySource, Cipher), String> {
+        match passphrase {
+            Some(passphrase) => {
+                if passphrase.chars().count() < 8 {
+                    return Err("口令至少需要 8 个字符".to_string());
+                }
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let cipher = Cipher::from_passphrase(passphrase, &salt)?;
+                let source = KeySource::Passphrase {
+                    salt: hex::encode(salt),
+                    verifier: STANDARD.encode(cipher.seal(VERIFIER)),
+                };
+                Ok((source, cipher))
+            }
+            None if self.config_dir.join(KEY_FILE).exists() => {
+                Ok((KeySource::KeyFile, self.read_key_file()?))
+            }
+            None => {
+                let mut key = [0u8; KEY_LEN];
+                OsRng.fill_bytes(&mut key);
+                write_private(&self.config_dir.join(KEY_FILE), &key)?;
+                Ok((KeySource::KeyFile, Cipher::from_key(&key)))
+            }
+        }
+    }
+
+    /// 关闭加密，需先解锁。全部记录和文件都解密回明文后才删除配置和密钥文件
+    pub fn disable(&self, store: &HistoryStore) -> Result<(), String> {
+        if self.source().is_none() {
+            return Ok(());
+        }
+        if !store.has_cipher() {
+            return Err("请先解锁历史记录".to_string());
+        }
+        store.reencrypt(None)?;
+        self.save(None)?;
+        let _ = fs::remove_file(self.config_dir.join(KEY_FILE));
+        Ok(())
+    }
+
+    /// 锁定期间暂存捕获的内容
+    pub fn defer(&self, payload: ClipboardPayload, image: Option<PendingImage>) {
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        let images = pending.iter().filter(|(_, image)| image.is_some()).count();
+        if image.is_some() && images >= MAX_PENDING_IMAGES {
+            if let Some(oldest) = pending.iter().position(|(_, image)| image.is_some()) {
+                pending.remove(oldest);
+            }
+        }
+        if pending.len() >= MAX_PENDING {
+            pending.remove(0);
+        }
+        pending.push((payload, image));
+    }
+
+    pub fn take_pending(&self) -> Vec<(ClipboardPayload, Option<PendingImage>)> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn read_key_file(&self) -> Result<Cipher, String> {
+        let key = fs::read(self.config_dir.join(KEY_FILE)).map_err(|e| e.to_string())?;
+        let key: [u8; KEY_LEN] = key
+            .try_into()
+            .map_err(|_| "History key file is corrupted".to_string())?;
+        Ok(Cipher::from_key(&key))
+    }
+}
+
+/// 写入仅当前用户可读写的文件（Unix 下权限为 0600）
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    file.write_all(data).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SourceApp;
+
+    #[test]
+    fn text_and_blobs_round_trip() {
+        let cipher = Cipher::from_key(&[7; KEY_LEN]);
+        let text = cipher.encrypt_text("hello");
+        assert!(text.starts_with(TEXT_PREFIX) && !text.contains("hello"));
+        assert_eq!(
+            decrypt_text(Some(&cipher), text.clone()).as_deref(),
+            Some("hello")
+        );
+        assert_eq!(decrypt_text(None, text), None);
+        assert_eq!(
+            decrypt_text(None, "plain".to_string()).as_deref(),
+            Some("plain")
+        );
+
+        let blob = cipher.encrypt_blob(b"png bytes");
+        assert!(is_encrypted_blob(&blob));
+        assert_eq!(
+            decrypt_blob(Some(&cipher), blob.clone()).unwrap(),
+            b"png bytes"
+        );
+        assert!(decrypt_blob(Some(&Cipher::from_key(&[8; KEY_LEN])), blob).is_err());
+    }
+
+    #[test]
+    fn enabling_encrypts_existing_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let payload = ClipboardPayload {
+            content_type: "text".to_string(),
+            content_hash: "hash".to_string(),
+            text_content: Some("Meeting notes".to_string()),
+            file_size: 13,
+            source_app: Some(SourceApp {
+                id: Some("editor".to_string()),
+                title: Some("Secret plan.txt".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let item = store.upsert(&payload).unwrap();
+
+        let state = EncryptionState::load(dir.path(), &store);
+        state.enable(&store, None).unwrap();
+        let (raw, raw_app): (String, String) = rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .query_row(
+                "SELECT text_content, source_app FROM clipboard_items",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(raw.starts_with(TEXT_PREFIX) && !raw.contains("Meeting"));
+        assert!(raw_app.starts_with(TEXT_PREFIX) && !raw_app.contains("Secret"));
+        // 数据库文件和 WAL 中都不再残留明文
+        for path in [db_path.clone(), dir.path().join("history.db-wal")] {
+            let bytes = fs::read(path).unwrap_or_default();
+            assert!(!bytes.windows(13).any(|w| w == b"Meeting notes"));
+        }
+
+        // 读取与搜索仍返回明文，按应用过滤也仍然有效
+        let found = store.search("meeting", 10, Some("editor")).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].text_content.as_deref(), Some("Meeting notes"));
+        assert_eq!(found[0].source_app, payload.source_app);
+        assert_eq!(store.list(10, Some("editor")).unwrap().len(), 1);
+        assert!(store.list(10, Some("browser")).unwrap().is_empty());
+
+        state.disable(&store).unwrap();
+        assert_eq!(
+            store.get(item.id).unwrap().unwrap().text_content.as_deref(),
+            Some("Meeting notes")
+        );
+    }
+
+    #[test]
+    fn enabling_keys_content_hashes_and_image_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let original = dir.path().join("abc.png");
+        let thumbnail = dir.path().join("abc_thumb.png");
+        fs::write(&original, b"png").unwrap();
+        fs::write(&thumbnail, b"thumb").unwrap();
+        let payload = ClipboardPayload {
+            content_type: "image".to_string(),
+            content_hash: "abc".to_string(),
+            image_path: Some(original.to_string_lossy().to_string()),
+            thumbnail_path: Some(thumbnail.to_string_lossy().to_string()),
+            file_size: 3,
+            ..Default::default()
+        };
+        let item = store.upsert(&payload).unwrap();
+        let raw_hash = |conn: &rusqlite::Connection| -> String {
+            conn.query_row("SELECT content_hash FROM clipboard_items", [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+
+        let state = EncryptionState::load(dir.path(), &store);
+        state.enable(&store, None).unwrap();
+        // 数据库和文件名中只有带密钥的 hash，无法由内容穷举
+        let key = store.content_key("abc");
+        assert_ne!(key, "abc");
+        assert_eq!(raw_hash(&conn), key);
+        assert!(!original.exists() && !thumbnail.exists());
+        let item_now = store.get(item.id).unwrap().unwrap();
+        assert_eq!(item_now.content_hash, "abc");
+        let renamed = dir.path().join(format!("{key}.png"));
+        assert_eq!(item_now.image_path.as_deref(), renamed.to_str());
+        assert_eq!(store.read_image(&renamed).unwrap(), b"png");
+        // 再次捕获相同内容仍按原始 hash 去重
+        assert!(store.contains_hash("abc").unwrap());
+        store.upsert(&payload).unwrap();
+        assert_eq!(store.list(10, None).unwrap().len(), 1);
+
+        state.disable(&store).unwrap();
+        assert_eq!(raw_hash(&conn), "abc");
+        let item_now = store.get(item.id).unwrap().unwrap();
+        assert_eq!(item_now.image_path.as_deref(), original.to_str());
+        assert_eq!(fs::read(&original).unwrap(), b"png");
+        assert_eq!(fs::read(&thumbnail).unwrap(), b"thumb");
+        assert!(!renamed.exists());
+    }
+
+    #[test]
+    fn disabling_keeps_the_key_when_anything_fails_to_decrypt() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        state.enable(&store, None).unwrap();
+        let payload = ClipboardPayload {
+            content_type: "text".to_string(),
+            content_hash: "hash".to_string(),
+            text_content: Some("Meeting notes".to_string()),
+            file_size: 13,
+            ..Default::default()
+        };
+        store.upsert(&payload).unwrap();
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_items (content_type, content_hash, text_content) VALUES ('text', 'other', 'enc1:broken')",
+            [],
+        )
+        .unwrap();
+
+        assert!(state.disable(&store).is_err());
+        assert!(dir.path().join(CONFIG_FILE).exists() && dir.path().join(KEY_FILE).exists());
+        assert!(state.status(&store).enabled);
+        let texts: Vec<String> = conn
+            .prepare("SELECT text_content FROM clipboard_items ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        // 回滚后已有记录仍是密文，无法解密的值也没有被清空
+        assert!(texts[0].starts_with(TEXT_PREFIX));
+        assert_eq!(texts[1], "enc1:broken");
+    }
+
+    #[test]
+    fn interrupted_enable_resumes_with_the_recorded_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        conn.execute(
+            "INSERT INTO clipboard_items (content_type, content_hash, text_content) VALUES ('text', 'broken', 'enc1:broken')",
+            [],
+        )
+        .unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        assert!(state.enable(&store, None).is_err());
+        // 密钥来源在改写任何数据之前已经记下
+        let key = fs::read(dir.path().join(KEY_FILE)).unwrap();
+        assert!(state.status(&store).incomplete);
+
+        conn.execute(
+            "DELETE FROM clipboard_items WHERE content_hash = 'broken'",
+            [],
+        )
+        .unwrap();
+        let store = HistoryStore::open(&db_path).unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        assert!(state.status(&store).incomplete && !state.is_locked(&store));
+        state.enable(&store, None).unwrap();
+        assert_eq!(fs::read(dir.path().join(KEY_FILE)).unwrap(), key);
+        assert!(!state.status(&store).incomplete);
+        assert!(state.enable(&store, None).is_err());
+    }
+
+    #[test]
+    fn locked_captures_keep_a_bounded_number_of_images_in_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let state = EncryptionState::load(dir.path(), &HistoryStore::open_in_memory().unwrap());
+        for i in 0..MAX_PENDING_IMAGES + 2 {
+            let image = arboard::ImageData {
+                width: 1,
+                height: 1,
+                bytes: vec![0; 4].into(),
+            };
+            let payload = ClipboardPayload {
+                content_type: "image".to_string(),
+                content_hash: i.to_string(),
+                file_size: 4,
+                ..Default::default()
+            };
+            let image = PendingImage::new(dir.path(), i.to_string(), image);
+            state.defer(payload, Some(image));
+        }
+
+        let pending = state.take_pending();
+        assert_eq!(pending.len(), MAX_PENDING_IMAGES);
+        assert_eq!(pending[0].0.content_hash, "2");
+        // 暂存期间不写出任何图片文件
+        assert!(pending
+            .iter()
+            .all(|(_, image)| !image.as_ref().unwrap().is_saved()));
+    }
+
+    #[test]
+    fn passphrase_unlocks_only_with_the_right_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        assert!(state.enable(&store, Some("short")).is_err());
+        state.enable(&store, Some("correct horse")).unwrap();
+
+        // 重新加载后处于锁定状态
+        let store = HistoryStore::open_in_memory().unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        assert!(state.is_locked(&store));
+        assert!(state.unlock(&store, "wrong horse").is_err());
+        assert!(state.is_locked(&store));
+        state.unlock(&store, "correct horse").unwrap();
+        assert!(!state.is_locked(&store));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn key_file_is_private_and_unlocks_on_load() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        EncryptionState::load(dir.path(), &store)
+            .enable(&store, None)
+            .unwrap();
+        let mode = fs::metadata(dir.path().join(KEY_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let store = HistoryStore::open_in_memory().unwrap();
+        let state = EncryptionState::load(dir.path(), &store);
+        assert_eq!(state.status(&store).mode, Some("key_file"));
+        assert!(!state.is_locked(&store));
+    }
+}
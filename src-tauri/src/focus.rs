@@ -1,3 +1,4 @@
+use crate::models::SourceApp;
 use std::sync::Mutex;
 
 // ============================================================================
@@ -11,6 +12,25 @@ use objc::{class, msg_send, sel, sel_impl};
 #[cfg(target_os = "macos")]
 static PREVIOUS_APP_PID: Mutex<Option<i32>> = Mutex::new(None);
 
+/// 唤出面板前的前台应用，粘贴时据此选择快捷键
+static PASTE_TARGET: Mutex<Option<SourceApp>> = Mutex::new(None);
+
+pub fn paste_target() -> Option<SourceApp> {
+    PASTE_TARGET.lock().ok()?.clone()
+}
+
+fn set_paste_target(app: Option<SourceApp>) {
+    if let Ok(mut guard) = PASTE_TARGET.lock() {
+        *guard = app;
+    }
+}
+
+/// 是否运行在 Wayland 会话中。此时无法激活其他应用的窗口，
+/// 隐藏自身后由合成器把焦点交还给之前的窗口
+pub fn is_wayland_session() -> bool {
+    cfg!(target_os = "linux") && std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
 /// 屏幕信息
 #[derive(Debug, Clone)]
 pub struct ScreenInfo {
@@ -95,10 +115,51 @@ pub fn save_frontmost_app() {
             if let Ok(mut guard) = PREVIOUS_APP_PID.lock() {
                 *guard = Some(pid);
             }
+            set_paste_target(frontmost_app());
+        }
+    }
+}
+
+/// 当前前台应用，复制内容时即为内容来源
+#[cfg(target_os = "macos")]
+pub fn frontmost_app() -> Option<SourceApp> {
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace.is_null() {
+            return None;
         }
+
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return None;
+        }
+
+        let pid: i32 = msg_send![app, processIdentifier];
+        let bundle_id: *mut Object = msg_send![app, bundleIdentifier];
+        let name: *mut Object = msg_send![app, localizedName];
+
+        Some(SourceApp {
+            id: nsstring_to_string(bundle_id),
+            name: nsstring_to_string(name),
+            window_class: None,
+            title: None,
+            pid: (pid > 0).then_some(pid as u32),
+        })
     }
 }
 
+#[cfg(target_os = "macos")]
+pub unsafe fn nsstring_to_string(string: *mut Object) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let utf8: *const std::os::raw::c_char = msg_send![string, UTF8String];
+    if utf8.is_null() {
+        return None;
+    }
+    Some(std::ffi::CStr::from_ptr(utf8).to_string_lossy().into_owned())
+}
+
 #[cfg(target_os = "macos")]
 pub fn restore_previous_app() {
     let pid = {
@@ -144,6 +205,16 @@ use windows::Win32::System::Threading::{AttachThreadInput, GetCurrentThreadId};
 use windows::Win32::UI::WindowsAndMessaging::GetWindowThreadProcessId;
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, SetForegroundWindow};
+#[cfg(target_os = "windows")]
+use windows::Win32::UI::WindowsAndMessaging::GetWindowTextW;
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::CloseHandle;
+#[cfg(target_os = "windows")]
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+#[cfg(target_os = "windows")]
+use windows::core::PWSTR;
 
 #[cfg(target_os = "windows")]
 static PREVIOUS_HWND: Mutex<Option<isize>> = Mutex::new(None);
@@ -156,10 +227,59 @@ pub fn save_frontmost_app() {
             if let Ok(mut guard) = PREVIOUS_HWND.lock() {
                 *guard = Some(hwnd.0 as isize);
             }
+            set_paste_target(frontmost_app());
         }
     }
 }
 
+/// 当前前台窗口及其进程
+#[cfg(target_os = "windows")]
+pub fn frontmost_app() -> Option<SourceApp> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
+        }
+
+        let mut title = [0u16; 512];
+        let len = GetWindowTextW(hwnd, &mut title);
+        let title = (len > 0).then(|| String::from_utf16_lossy(&title[..len as usize]));
+
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32));
+        let exe = if pid != 0 { process_image_path(pid) } else { None };
+        let exe = exe.as_deref().map(std::path::Path::new);
+
+        Some(SourceApp {
+            id: exe
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string()),
+            name: exe
+                .and_then(|p| p.file_stem())
+                .map(|n| n.to_string_lossy().to_string()),
+            window_class: None,
+            title,
+            pid: (pid != 0).then_some(pid),
+        })
+    }
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn process_image_path(pid: u32) -> Option<String> {
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+    let mut buffer = [0u16; 1024];
+    let mut size = buffer.len() as u32;
+    let result = QueryFullProcessImageNameW(
+        handle,
+        PROCESS_NAME_WIN32,
+        PWSTR(buffer.as_mut_ptr()),
+        &mut size,
+    );
+    let _ = CloseHandle(handle);
+    result.ok()?;
+    Some(String::from_utf16_lossy(&buffer[..size as usize]))
+}
+
 #[cfg(target_os = "windows")]
 pub fn restore_previous_app() {
     let hwnd_value = {
@@ -197,46 +317,191 @@ pub fn restore_previous_app() {
 }
 
 // ============================================================================
-// Linux Implementation (X11 via xdotool)
+// Linux Implementation (X11 via EWMH)
 // ============================================================================
 #[cfg(target_os = "linux")]
-static PREVIOUS_WINDOW_ID: Mutex<Option<String>> = Mutex::new(None);
+static PREVIOUS_WINDOW_ID: Mutex<Option<u32>> = Mutex::new(None);
+
+/// 等待窗口管理器完成激活的最长时间
+#[cfg(target_os = "linux")]
+const ACTIVATE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
 
 #[cfg(target_os = "linux")]
 pub fn save_frontmost_app() {
-    // Use xdotool to get active window ID
-    if let Ok(output) = std::process::Command::new("xdotool")
-        .args(["getactivewindow"])
-        .output()
-    {
-        if output.status.success() {
-            let window_id = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !window_id.is_empty() {
-                if let Ok(mut guard) = PREVIOUS_WINDOW_ID.lock() {
-                    *guard = Some(window_id);
-                }
-            }
-        }
+    use x11rb::connection::Connection;
+
+    // XWayland 只能看到 X11 窗口，激活它们会把焦点从原生 Wayland 窗口抢走
+    if is_wayland_session() {
+        set_paste_target(None);
+        return;
+    }
+    let Ok((conn, screen_num)) = x11rb::connect(None) else {
+        return;
+    };
+    let root = conn.setup().roots[screen_num].root;
+    let Some(window) = x11_active_window(&conn, root) else {
+        return;
+    };
+    // 自己的窗口不作为恢复目标
+    if x11_window_pid(&conn, window) == Some(std::process::id()) {
+        return;
+    }
+    if let Ok(mut guard) = PREVIOUS_WINDOW_ID.lock() {
+        *guard = Some(window);
     }
+    set_paste_target(x11_window_app(&conn, window));
 }
 
+/// 激活之前保存的窗口，返回时焦点已经切换（或已超时），可以立即发送按键
 #[cfg(target_os = "linux")]
 pub fn restore_previous_app() {
-    let window_id = {
+    use x11rb::connection::Connection;
+
+    let window = {
         match PREVIOUS_WINDOW_ID.lock() {
-            Ok(guard) => guard.clone(),
+            Ok(mut guard) => guard.take(),
             Err(_) => None,
         }
     };
 
-    let Some(window_id) = window_id else { return };
+    let Some(window) = window else { return };
+    let Ok((conn, screen_num)) = x11rb::connect(None) else {
+        return;
+    };
+    let root = conn.setup().roots[screen_num].root;
+    x11_activate_window(&conn, root, window);
+}
 
-    // Use xdotool to activate the window
-    let _ = std::process::Command::new("xdotool")
-        .args(["windowactivate", &window_id])
-        .spawn();
+/// 通过 EWMH _NET_ACTIVE_WINDOW 请求窗口管理器激活窗口并等待生效。
+/// 没有支持 EWMH 的窗口管理器时直接设置输入焦点。返回焦点是否已在该窗口
+#[cfg(target_os = "linux")]
+pub fn x11_activate_window(
+    conn: &impl x11rb::connection::Connection,
+    root: x11rb::protocol::xproto::Window,
+    window: x11rb::protocol::xproto::Window,
+) -> bool {
+    use std::time::{Duration, Instant};
+    use x11rb::protocol::xproto::{
+        ClientMessageEvent, ConfigureWindowAux, ConnectionExt, EventMask, InputFocus, StackMode,
+    };
+    use x11rb::CURRENT_TIME;
+
+    let ewmh = x11_atom(conn, b"_NET_ACTIVE_WINDOW")
+        .filter(|atom| x11_property(conn, root, *atom).is_some());
+    if let Some(atom) = ewmh {
+        // 来源 2 表示代表用户操作的请求，窗口管理器不会当作抢焦点而忽略
+        let event = ClientMessageEvent::new(32, window, atom, [2, CURRENT_TIME, 0, 0, 0]);
+        let mask = EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY;
+        if conn.send_event(false, root, mask, event).is_ok() && conn.flush().is_ok() {
+            let deadline = Instant::now() + ACTIVATE_TIMEOUT;
+            while Instant::now() < deadline {
+                if x11_active_window(conn, root) == Some(window) {
+                    return true;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    }
 
-    if let Ok(mut guard) = PREVIOUS_WINDOW_ID.lock() {
-        *guard = None;
+    let aux = ConfigureWindowAux::new().stack_mode(StackMode::ABOVE);
+    let _ = conn.configure_window(window, &aux);
+    let _ = conn.set_input_focus(InputFocus::PARENT, window, CURRENT_TIME);
+    conn.get_input_focus()
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .is_some_and(|reply| reply.focus == window)
+}
+
+/// 当前激活的 X11 窗口（EWMH _NET_ACTIVE_WINDOW）对应的应用
+#[cfg(target_os = "linux")]
+pub fn frontmost_app() -> Option<SourceApp> {
+    use x11rb::connection::Connection;
+
+    let (conn, screen_num) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots[screen_num].root;
+    let window = x11_active_window(&conn, root)?;
+    x11_window_app(&conn, window)
+}
+
+#[cfg(target_os = "linux")]
+pub fn x11_active_window(
+    conn: &impl x11rb::connection::Connection,
+    root: x11rb::protocol::xproto::Window,
+) -> Option<x11rb::protocol::xproto::Window> {
+    let atom = x11_atom(conn, b"_NET_ACTIVE_WINDOW")?;
+    let value = x11_property(conn, root, atom)?;
+    let window = u32::from_ne_bytes(value.get(..4)?.try_into().ok()?);
+    (window != x11rb::NONE).then_some(window)
+}
+
+/// 读取窗口的 WM_CLASS、_NET_WM_PID 和标题，两者都没有时说明不是应用窗口
+#[cfg(target_os = "linux")]
+pub fn x11_window_app(
+    conn: &impl x11rb::connection::Connection,
+    window: x11rb::protocol::xproto::Window,
+) -> Option<SourceApp> {
+    use x11rb::protocol::xproto::AtomEnum;
+
+    // WM_CLASS 为 "instance\0class\0"，取 class
+    let window_class = x11_property(conn, window, AtomEnum::WM_CLASS.into()).and_then(|value| {
+        let mut parts = value.split(|b| *b == 0).filter(|part| !part.is_empty());
+        let instance = parts.next();
+        String::from_utf8(parts.next().or(instance)?.to_vec()).ok()
+    });
+    let pid = x11_window_pid(conn, window);
+    if window_class.is_none() && pid.is_none() {
+        return None;
     }
+
+    let title = x11_atom(conn, b"_NET_WM_NAME")
+        .and_then(|atom| x11_property(conn, window, atom))
+        .or_else(|| x11_property(conn, window, AtomEnum::WM_NAME.into()))
+        .map(|value| String::from_utf8_lossy(&value).to_string())
+        .filter(|title| !title.is_empty());
+    let process = pid.and_then(|pid| {
+        std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|comm| comm.trim().to_string())
+            .filter(|comm| !comm.is_empty())
+    });
+
+    Some(SourceApp {
+        id: process.clone().or_else(|| window_class.clone()),
+        name: window_class.clone().or(process),
+        window_class,
+        title,
+        pid,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn x11_window_pid(
+    conn: &impl x11rb::connection::Connection,
+    window: x11rb::protocol::xproto::Window,
+) -> Option<u32> {
+    let value = x11_property(conn, window, x11_atom(conn, b"_NET_WM_PID")?)?;
+    Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+}
+
+#[cfg(target_os = "linux")]
+fn x11_atom(conn: &impl x11rb::connection::Connection, name: &[u8]) -> Option<u32> {
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    Some(conn.intern_atom(true, name).ok()?.reply().ok()?.atom).filter(|atom| *atom != x11rb::NONE)
+}
+
+#[cfg(target_os = "linux")]
+fn x11_property(
+    conn: &impl x11rb::connection::Connection,
+    window: x11rb::protocol::xproto::Window,
+    property: u32,
+) -> Option<Vec<u8>> {
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+
+    let reply = conn
+        .get_property(false, window, property, AtomEnum::ANY, 0, 1024)
+        .ok()?
+        .reply()
+        .ok()?;
+    (reply.type_ != x11rb::NONE).then_some(reply.value)
 }
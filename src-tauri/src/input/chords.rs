@@ -0,0 +1,91 @@
+use crate::models::{PasteChord, PasteChordRule, SourceApp};
+
+/// 内置的终端粘贴方式，用户可以在设置中修改。
+/// Linux 按 WM_CLASS 匹配，Windows 按进程名匹配
+pub fn default_rules() -> Vec<PasteChordRule> {
+    [
+        ("gnome-terminal", PasteChord::CtrlShiftV),
+        ("konsole", PasteChord::CtrlShiftV),
+        ("kitty", PasteChord::CtrlShiftV),
+        ("alacritty", PasteChord::CtrlShiftV),
+        ("xfce4-terminal", PasteChord::CtrlShiftV),
+        ("tilix", PasteChord::CtrlShiftV),
+        ("terminator", PasteChord::CtrlShiftV),
+        ("org.wezfurlong.wezterm", PasteChord::CtrlShiftV),
+        // xterm 和 urxvt 默认只能粘贴 PRIMARY 选区
+        ("xterm", PasteChord::MiddleClick),
+        ("urxvt", PasteChord::MiddleClick),
+        ("mintty", PasteChord::ShiftInsert),
+    ]
+    .into_iter()
+    .map(|(app, chord)| PasteChordRule {
+        app: app.to_string(),
+        chord,
+    })
+    .collect()
+}
+
+/// 第一条命中目标应用的规则决定粘贴方式，目标未知或未命中时使用 Ctrl+V
+pub fn chord_for(rules: &[PasteChordRule], app: Option<&SourceApp>) -> PasteChord {
+    app.and_then(|app| rules.iter().find(|rule| matches(rule, app)))
+        .map_or(PasteChord::CtrlV, |rule| rule.chord)
+}
+
+fn matches(rule: &PasteChordRule, app: &SourceApp) -> bool {
+    let name = rule.app.trim();
+    !name.is_empty()
+        && [&app.window_class, &app.id, &app.name].iter().any(|value| {
+            value
+                .as_deref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(name))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(window_class: Option<&str>, id: Option<&str>) -> SourceApp {
+        SourceApp {
+            id: id.map(str::to_string),
+            name: window_class.or(id).map(str::to_string),
+            window_class: window_class.map(str::to_string),
+            title: None,
+            pid: None,
+        }
+    }
+
+    #[test]
+    fn terminals_get_their_own_chord() {
+        let rules = default_rules();
+        let gnome = app(Some("Gnome-terminal"), Some("gnome-terminal-server"));
+        assert_eq!(chord_for(&rules, Some(&gnome)), PasteChord::CtrlShiftV);
+        let xterm = app(Some("XTerm"), Some("xterm"));
+        assert_eq!(chord_for(&rules, Some(&xterm)), PasteChord::MiddleClick);
+        // Windows 下 id 为可执行文件名，name 为去掉扩展名的部分
+        let mintty = SourceApp {
+            name: Some("mintty".to_string()),
+            ..app(None, Some("mintty.exe"))
+        };
+        assert_eq!(chord_for(&rules, Some(&mintty)), PasteChord::ShiftInsert);
+    }
+
+    #[test]
+    fn other_or_unknown_targets_use_ctrl_v() {
+        let rules = default_rules();
+        let editor = app(Some("Gedit"), Some("gedit"));
+        assert_eq!(chord_for(&rules, Some(&editor)), PasteChord::CtrlV);
+        assert_eq!(chord_for(&rules, None), PasteChord::CtrlV);
+    }
+
+    #[test]
+    fn earlier_user_rules_override_the_builtin_table() {
+        let mut rules = vec![PasteChordRule {
+            app: "kitty".to_string(),
+            chord: PasteChord::ShiftInsert,
+        }];
+        rules.extend(default_rules());
+        let kitty = app(Some("kitty"), Some("kitty"));
+        assert_eq!(chord_for(&rules, Some(&kitty)), PasteChord::ShiftInsert);
+    }
+}
@@ -0,0 +1,262 @@
+mod chords;
+mod typing;
+#[cfg(target_os = "linux")]
+mod uinput;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use crate::models::PasteChord;
+#[cfg(not(target_os = "linux"))]
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+pub use chords::{chord_for, default_rules as default_paste_chords};
+pub use typing::TypingState;
+
+/// 向当前焦点窗口发送粘贴快捷键 (Cmd+V / Ctrl+V)
+#[cfg(target_os = "macos")]
+pub fn send_paste(_chord: PasteChord) -> Result<(), String> {
+    // 使用 AppleScript 模拟 Cmd+V
+    let script = r#"tell application "System Events" to keystroke "v" using command down"#;
+    let output = Command::new("osascript")
+        .args(["-e", script])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn send_paste(chord: PasteChord) -> Result<(), String> {
+    // Windows: 使用 PowerShell 模拟按键，没有 PRIMARY 选区，中键粘贴按 Ctrl+V 处理
+    let keys = match chord {
+        PasteChord::CtrlV | PasteChord::MiddleClick | PasteChord::TypeText => "^v",
+        PasteChord::CtrlShiftV => "^+v",
+        PasteChord::ShiftInsert => "+{INSERT}",
+    };
+    let script = format!("Add-Type -AssemblyName System.Windows.Forms; [System.Windows.Forms.SendKeys]::SendWait('{keys}')");
+    let output = Command::new("powershell")
+        .args(["-Command", &script])
+        .output()
+        .map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(())
+}
+
+/// Linux: 在进程内模拟粘贴快捷键或鼠标中键。逐字输入需要条目文本，由 type_text 处理
+#[cfg(target_os = "linux")]
+pub fn send_paste(chord: PasteChord) -> Result<(), String> {
+    use keysym::*;
+
+    match chord {
+        PasteChord::CtrlV | PasteChord::TypeText => inject(Stroke::Chord(&[CONTROL_L, V])),
+        PasteChord::CtrlShiftV => inject(Stroke::Chord(&[CONTROL_L, SHIFT_L, V])),
+        PasteChord::ShiftInsert => inject(Stroke::Chord(&[SHIFT_L, INSERT])),
+        PasteChord::MiddleClick => inject(Stroke::MiddleClick),
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+enum Stroke<'a> {
+    /// 依次按下再逆序松开的组合键
+    Chord(&'a [keysym::Keysym]),
+    MiddleClick,
+    Text(&'a Typing<'a>),
+}
+
+/// macOS: 通过 AppleScript 逐字符 keystroke，中止时结束脚本进程
+#[cfg(target_os = "macos")]
+fn type_text(text: &str, delay: Duration, abort: &AtomicBool) -> Result<(), String> {
+    let script = format!(
+        r#"on run argv
+    tell application "System Events"
+        repeat with c in characters of (item 1 of argv)
+            if (c as text) is linefeed then
+                key code 36
+            else
+                keystroke (c as text)
+            end if
+            delay {}
+        end repeat
+    end tell
+end run"#,
+        delay.as_secs_f64()
+    );
+    let child = Command::new("osascript")
+        .args(["-e", &script, "--", text])
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    wait_unless_aborted(child, abort)
+}
+
+/// Windows: PowerShell 从标准输入读取文本后逐字符 SendKeys，中止时结束进程
+#[cfg(target_os = "windows")]
+fn type_text(text: &str, delay: Duration, abort: &AtomicBool) -> Result<(), String> {
+    use std::io::Write;
+
+    // SendKeys 中 + ^ % ~ 和括号有特殊含义，需要用花括号转义
+    let script = format!(
+        r#"Add-Type -AssemblyName System.Windows.Forms
+[Console]::InputEncoding = [Text.Encoding]::UTF8
+foreach ($c in [Console]::In.ReadToEnd().ToCharArray()) {{
+    if ($c -eq "`r") {{ continue }}
+    if ($c -eq "`n") {{ $k = '{{ENTER}}' }} elseif ('+^%~(){{}}[]'.Contains($c)) {{ $k = "{{$c}}" }} else {{ $k = [string]$c }}
+    [System.Windows.Forms.SendKeys]::SendWait($k)
+    Start-Sleep -Milliseconds {}
+}}"#,
+        delay.as_millis()
+    );
+    let mut child = Command::new("powershell")
+        .args(["-Command", &script])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    wait_unless_aborted(child, abort)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn wait_unless_aborted(mut child: Child, abort: &AtomicBool) -> Result<(), String> {
+    loop {
+        if abort.load(Ordering::SeqCst) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(());
+        }
+        if child.try_wait().map_err(|e| e.to_string())?.is_some() {
+            let output = child.wait_with_output().map_err(|e| e.to_string())?;
+            if !output.status.success() {
+                return Err(String::from_utf8_lossy(&output.stderr).to_string());
+            }
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Linux: 逐字符模拟按键，字符之间等待 delay
+#[cfg(target_os = "linux")]
+fn type_text(text: &str, delay: Duration, abort: &AtomicBool) -> Result<(), String> {
+    inject(Stroke::Text(&Typing {
+        text,
+        delay,
+        abort,
+        typed: std::cell::Cell::new(0),
+    }))
+}
+
+/// 一次逐字输入的进度，后端每输入一个字符前调用 next
+#[cfg(target_os = "linux")]
+struct Typing<'a> {
+    text: &'a str,
+    delay: Duration,
+    abort: &'a AtomicBool,
+    typed: std::cell::Cell<usize>,
+}
+
+#[cfg(target_os = "linux")]
+impl Typing<'_> {
+    /// 要输入的 keysym，无法输入的控制字符跳过
+    fn keysyms(&self) -> Vec<keysym::Keysym> {
+        self.text.chars().filter_map(keysym::from_char).collect()
+    }
+
+    /// 等待字符间隔，已中止时返回 false
+    fn next(&self) -> bool {
+        if self.typed.get() > 0 {
+            std::thread::sleep(self.delay);
+        }
+        if self.abort.load(Ordering::SeqCst) {
+            return false;
+        }
+        self.typed.set(self.typed.get() + 1);
+        true
+    }
+
+    /// 已经输入过字符，失败时不能再换一种方式从头输入
+    fn started(&self) -> bool {
+        self.typed.get() > 0
+    }
+}
+
+/// X11 keysym，取值见 X11/keysymdef.h，各种注入方式都用它描述按键
+#[cfg(target_os = "linux")]
+pub mod keysym {
+    pub type Keysym = u32;
+
+    pub const SHIFT_L: Keysym = 0xffe1;
+    pub const CONTROL_L: Keysym = 0xffe3;
+    pub const INSERT: Keysym = 0xff63;
+    pub const V: Keysym = 0x0076;
+    pub const TAB: Keysym = 0xff09;
+    pub const RETURN: Keysym = 0xff0d;
+
+    /// 字符对应的 keysym：Latin-1 字符与码位相同，其余 Unicode 字符为 0x01000000 + 码位
+    pub fn from_char(c: char) -> Option<Keysym> {
+        match c {
+            '\n' => Some(RETURN),
+            '\t' => Some(TAB),
+            c if c.is_control() => None,
+            ' '..='~' | '\u{a0}'..='\u{ff}' => Some(c as Keysym),
+            c => Some(0x0100_0000 + c as Keysym),
+        }
+    }
+}
+
+/// 按会话类型依次尝试可用的注入方式，可通过 SUPER_PASTE_INPUT_BACKEND 强制指定
+/// (`virtual-keyboard` / `uinput` / `x11`)，便于在无头合成器下测试。
+/// Wayland 原生窗口收不到 XTest 事件，Wayland 会话下不使用 X11
+#[cfg(target_os = "linux")]
+fn inject(stroke: Stroke) -> Result<(), String> {
+    let forced = std::env::var("SUPER_PASTE_INPUT_BACKEND").ok();
+    let candidates = match forced.as_deref() {
+        Some(name) => vec![name],
+        None if crate::focus::is_wayland_session() => vec!["virtual-keyboard", "uinput"],
+        None => vec!["x11", "uinput"],
+    };
+
+    let mut errors = Vec::new();
+    for name in candidates {
+        let result = match (name, stroke) {
+            ("virtual-keyboard", Stroke::Chord(keys)) => wayland::send_chord(keys),
+            ("virtual-keyboard", Stroke::MiddleClick) => {
+                Err("virtual keyboard cannot click".to_string())
+            }
+            ("uinput", Stroke::Chord(keys)) => uinput::send_chord(keys),
+            ("uinput", Stroke::MiddleClick) => uinput::click_middle(),
+            ("x11", Stroke::Chord(keys)) => x11::send_chord(keys),
+            ("x11", Stroke::MiddleClick) => x11::click_middle(),
+            ("virtual-keyboard", Stroke::Text(typing)) => wayland::type_text(typing),
+            ("uinput", Stroke::Text(typing)) => uinput::type_text(typing),
+            ("x11", Stroke::Text(typing)) => x11::type_text(typing),
+            _ => Err("unknown input backend".to_string()),
+        };
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if matches!(stroke, Stroke::Text(typing) if typing.started()) => {
+                return Err(format!("逐字输入中断：{e}"));
+            }
+            Err(e) => errors.push(format!("{name}: {e}")),
+        }
+    }
+    let details = errors.join("; ");
+    Err(if crate::focus::is_wayland_session() {
+        format!("当前 Wayland 会话无法模拟按键：合成器需支持 zwp_virtual_keyboard_v1，或为当前用户开放 /dev/uinput 的写权限（{details}）")
+    } else {
+        format!("无法模拟按键（{details}）")
+    })
+}
@@ -0,0 +1,65 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+/// 逐字输入的运行状态。输入期间注册中止快捷键，同一时间只允许一次输入
+#[derive(Default)]
+pub struct TypingState {
+    busy: AtomicBool,
+    abort: AtomicBool,
+    shortcut: Mutex<Option<Shortcut>>,
+}
+
+impl TypingState {
+    /// 逐字输入 text，阻塞到输完或被中止
+    pub fn run(
+        &self,
+        app: &tauri::AppHandle,
+        text: &str,
+        delay: Duration,
+        abort_shortcut: &str,
+    ) -> Result<(), String> {
+        if self.busy.swap(true, Ordering::SeqCst) {
+            return Err("正在逐字输入其他内容".to_string());
+        }
+        self.abort.store(false, Ordering::SeqCst);
+        if let Err(e) = self.register(app, abort_shortcut) {
+            self.busy.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+
+        let result = super::type_text(text, delay, &self.abort);
+
+        if let Some(shortcut) = self.lock_shortcut().take() {
+            let _ = app.global_shortcut().unregister(shortcut);
+        }
+        self.busy.store(false, Ordering::SeqCst);
+        result
+    }
+
+    /// 是否为输入期间注册的中止快捷键
+    pub fn is_abort_shortcut(&self, shortcut: &Shortcut) -> bool {
+        self.lock_shortcut().as_ref() == Some(shortcut)
+    }
+
+    pub fn abort(&self) {
+        self.abort.store(true, Ordering::SeqCst);
+    }
+
+    /// 快捷键无效或被占用时不开始输入，否则输入过程无法中途中止
+    fn register(&self, app: &tauri::AppHandle, accelerator: &str) -> Result<(), String> {
+        let shortcut = accelerator
+            .parse::<Shortcut>()
+            .map_err(|e| format!("中止输入快捷键 {} 无效：{}", accelerator, e))?;
+        app.global_shortcut()
+            .register(shortcut)
+            .map_err(|e| format!("无法注册中止输入快捷键 {}：{}", accelerator, e))?;
+        *self.lock_shortcut() = Some(shortcut);
+        Ok(())
+    }
+
+    fn lock_shortcut(&self) -> std::sync::MutexGuard<'_, Option<Shortcut>> {
+        self.shortcut.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,213 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::thread;
+use std::time::Duration;
+
+use super::keysym::{self, Keysym};
+use super::Typing;
+
+const UINPUT_PATH: &str = "/dev/uinput";
+
+/// 新设备要等合成器识别后才能收到按键，太早发送的事件会丢失
+const SETTLE: Duration = Duration::from_millis(200);
+
+// 取值见 linux/input-event-codes.h 和 linux/uinput.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const BTN_MIDDLE: u16 = 0x112;
+const BUS_VIRTUAL: u16 = 0x06;
+const UI_SET_EVBIT: u32 = 0x4004_5564;
+const UI_SET_KEYBIT: u32 = 0x4004_5565;
+const UI_SET_RELBIT: u32 = 0x4004_5566;
+const UI_DEV_SETUP: u32 = 0x405c_5503;
+const UI_DEV_CREATE: u32 = 0x5501;
+const UI_DEV_DESTROY: u32 = 0x5502;
+
+/// 美式布局各排按键的字符，依次为不按 Shift、按住 Shift 时的字符和该排第一个键的键码
+const US_ROWS: [(&str, &str, u16); 4] = [
+    ("1234567890-=", "!@#$%^&*()_+", 2),
+    ("qwertyuiop[]", "QWERTYUIOP{}", 16),
+    ("asdfghjkl;'`", "ASDFGHJKL:\"~", 30),
+    ("\\zxcvbnm,./", "|ZXCVBNM<>?", 43),
+];
+const KEY_SHIFT: u16 = 42;
+
+/// keysym 对应的 evdev 键码。uinput 模拟的是物理按键，按美式布局的位置发送
+fn evdev_code(keysym: Keysym) -> Option<u16> {
+    match keysym {
+        keysym::SHIFT_L => Some(KEY_SHIFT),
+        keysym::CONTROL_L => Some(29),
+        keysym::INSERT => Some(110),
+        keysym => us_key(keysym).map(|(code, _)| code),
+    }
+}
+
+/// 美式布局下输入该 keysym 的键码，以及是否需要按住 Shift
+fn us_key(keysym: Keysym) -> Option<(u16, bool)> {
+    match keysym {
+        keysym::RETURN => return Some((28, false)),
+        keysym::TAB => return Some((15, false)),
+        0x20 => return Some((57, false)),
+        _ => {}
+    }
+    let c = char::from_u32(keysym).filter(char::is_ascii_graphic)?;
+    US_ROWS.iter().find_map(|(plain, shifted, first)| {
+        plain
+            .find(c)
+            .map(|index| (first + index as u16, false))
+            .or_else(|| shifted.find(c).map(|index| (first + index as u16, true)))
+    })
+}
+
+/// 创建一个临时的 uinput 键盘发送组合键，需要 /dev/uinput 的写权限
+pub fn send_chord(keys: &[Keysym]) -> Result<(), String> {
+    let codes = keys
+        .iter()
+        .map(|keysym| {
+            evdev_code(*keysym).ok_or_else(|| format!("No evdev key for keysym {keysym:#x}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    press(&codes, false)
+}
+
+/// 点击鼠标中键。设备需要带相对坐标轴才会被识别为鼠标
+pub fn click_middle() -> Result<(), String> {
+    press(&[BTN_MIDDLE], true)
+}
+
+/// 逐字符输入，只能输入美式布局上的 ASCII 字符，且系统布局需为美式
+pub fn type_text(typing: &Typing) -> Result<(), String> {
+    let keys = typing
+        .keysyms()
+        .into_iter()
+        .map(|keysym| {
+            us_key(keysym).ok_or_else(|| format!("Cannot type keysym {keysym:#x} with uinput"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut codes: Vec<u16> = keys.iter().map(|(code, _)| *code).collect();
+    codes.push(KEY_SHIFT);
+    codes.sort_unstable();
+    codes.dedup();
+
+    let mut device = open_device(&codes, false)?;
+    let mut result = Ok(());
+    for (code, shift) in keys {
+        if !typing.next() {
+            break;
+        }
+        let mut events = vec![(code, 1), (code, 0)];
+        if shift {
+            events.insert(0, (KEY_SHIFT, 1));
+            events.push((KEY_SHIFT, 0));
+        }
+        result = events
+            .into_iter()
+            .try_for_each(|(code, value)| emit(&mut device, code, value));
+        if result.is_err() {
+            break;
+        }
+    }
+    close_device(&device)?;
+    result
+}
+
+/// 依次按下各键再逆序松开，pointer 为 true 时创建鼠标设备
+fn press(codes: &[u16], pointer: bool) -> Result<(), String> {
+    let mut device = open_device(codes, pointer)?;
+    let result = codes
+        .iter()
+        .map(|code| (*code, 1))
+        .chain(codes.iter().rev().map(|code| (*code, 0)))
+        .try_for_each(|(code, value)| emit(&mut device, code, value));
+    close_device(&device)?;
+    result
+}
+
+/// 创建只带 codes 这些键的设备，等合成器识别后返回
+fn open_device(codes: &[u16], pointer: bool) -> Result<File, String> {
+    let device = OpenOptions::new()
+        .write(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(UINPUT_PATH)
+        .map_err(|e| format!("Cannot open {UINPUT_PATH}: {e}"))?;
+    create_device(&device, codes, pointer)?;
+    thread::sleep(SETTLE);
+    Ok(device)
+}
+
+fn close_device(device: &File) -> Result<(), String> {
+    // 给合成器留出读取事件的时间再移除设备
+    thread::sleep(Duration::from_millis(20));
+    ioctl(device, UI_DEV_DESTROY, 0)
+}
+
+/// 发送一个按键事件及随后的同步事件
+fn emit(device: &mut File, code: u16, value: i32) -> Result<(), String> {
+    write_event(device, EV_KEY, code, value)?;
+    write_event(device, EV_SYN, SYN_REPORT, 0)
+}
+
+fn create_device(device: &File, codes: &[u16], pointer: bool) -> Result<(), String> {
+    ioctl(device, UI_SET_EVBIT, EV_KEY as libc::c_ulong)?;
+    for code in codes {
+        ioctl(device, UI_SET_KEYBIT, *code as libc::c_ulong)?;
+    }
+    if pointer {
+        ioctl(device, UI_SET_EVBIT, EV_REL as libc::c_ulong)?;
+        ioctl(device, UI_SET_RELBIT, REL_X as libc::c_ulong)?;
+        ioctl(device, UI_SET_RELBIT, REL_Y as libc::c_ulong)?;
+    }
+
+    let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+    setup.id.bustype = BUS_VIRTUAL;
+    setup.id.version = 1;
+    for (dst, src) in setup.name.iter_mut().zip(b"Super Paste virtual input") {
+        *dst = *src as libc::c_char;
+    }
+    ioctl(device, UI_DEV_SETUP, &setup as *const _ as libc::c_ulong)?;
+    ioctl(device, UI_DEV_CREATE, 0)
+}
+
+fn ioctl(device: &File, request: u32, arg: libc::c_ulong) -> Result<(), String> {
+    if unsafe { libc::ioctl(device.as_raw_fd(), request as _, arg) } < 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+fn write_event(device: &mut File, type_: u16, code: u16, value: i32) -> Result<(), String> {
+    let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+    event.type_ = type_;
+    event.code = code;
+    event.value = value;
+    let bytes = unsafe {
+        std::slice::from_raw_parts(
+            &event as *const libc::input_event as *const u8,
+            std::mem::size_of::<libc::input_event>(),
+        )
+    };
+    device.write_all(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_characters_map_to_us_layout_keys() {
+        assert_eq!(us_key('a' as Keysym), Some((30, false)));
+        assert_eq!(us_key('A' as Keysym), Some((30, true)));
+        assert_eq!(us_key('"' as Keysym), Some((40, true)));
+        assert_eq!(us_key('\\' as Keysym), Some((43, false)));
+        assert_eq!(us_key('/' as Keysym), Some((53, false)));
+        assert_eq!(us_key(keysym::RETURN), Some((28, false)));
+        assert_eq!(us_key(keysym::from_char('é').unwrap()), None);
+        assert_eq!(evdev_code(keysym::V), Some(47));
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::{AsFd, FromRawFd};
+use std::time::{SystemTime, UNIX_EPOCH};
+use wayland_client::protocol::{wl_keyboard, wl_registry, wl_seat};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+
+use super::keysym::Keysym;
+use super::Typing;
+
+mod protocol {
+    //! wayland-protocols 没有收录 virtual-keyboard-unstable-v1，从协议文件生成绑定
+    #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+    #![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+    #![allow(missing_docs, clippy::all)]
+
+    use wayland_client;
+    use wayland_client::protocol::*;
+
+    pub mod __interfaces {
+        // 生成的接口表引用 wayland_backend，用 wayland-client 重新导出的版本，不单独依赖
+        use wayland_client::backend as wayland_backend;
+        use wayland_client::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/virtual-keyboard-unstable-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/virtual-keyboard-unstable-v1.xml");
+}
+
+use protocol::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use protocol::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+
+#[derive(Default)]
+struct State {
+    seat: Option<wl_seat::WlSeat>,
+    manager: Option<ZwpVirtualKeyboardManagerV1>,
+}
+
+/// 一个 keymap 中最多放的键数，保证键码不超过 X11 客户端能处理的 255
+const MAX_KEYMAP_KEYS: usize = 200;
+
+/// 通过 zwp_virtual_keyboard_v1 创建一个临时键盘发送组合键。
+/// 键盘只带本次用到的键，由 keymap 决定 keysym，与用户的键盘布局无关
+pub fn send_chord(keys: &[Keysym]) -> Result<(), String> {
+    let mut keyboard = VirtualKeyboard::connect()?;
+    keyboard.set_keymap(keys)?;
+
+    // 修饰键的状态需要单独告知合成器
+    let mut mods = 0;
+    for (index, key) in keys.iter().enumerate() {
+        keyboard.key(index, wl_keyboard::KeyState::Pressed);
+        if let Some((_, mask)) = modifier(*key) {
+            mods |= mask;
+            keyboard.keyboard.modifiers(mods, 0, 0, 0);
+        }
+    }
+    for (index, key) in keys.iter().enumerate().rev() {
+        keyboard.key(index, wl_keyboard::KeyState::Released);
+        if let Some((_, mask)) = modifier(*key) {
+            mods &= !mask;
+            keyboard.keyboard.modifiers(mods, 0, 0, 0);
+        }
+    }
+    keyboard.finish()
+}
+
+/// 逐字符输入。每个字符在 keymap 中都有自己的键，不需要修饰键；
+/// 字符种类超过单个 keymap 的容量时分段上传
+pub fn type_text(typing: &Typing) -> Result<(), String> {
+    let mut keyboard = VirtualKeyboard::connect()?;
+    'segments: for (keys, indexes) in segments(&typing.keysyms(), MAX_KEYMAP_KEYS) {
+        keyboard.set_keymap(&keys)?;
+        for index in indexes {
+            if !typing.next() {
+                break 'segments;
+            }
+            keyboard.key(index, wl_keyboard::KeyState::Pressed);
+            keyboard.key(index, wl_keyboard::KeyState::Released);
+            keyboard.conn.flush().map_err(|e| e.to_string())?;
+        }
+    }
+    keyboard.finish()
+}
+
+/// 把 keysym 序列切成若干段，每段给出不超过 max 个不同的 keysym 及各字符在其中的下标
+fn segments(keysyms: &[Keysym], max: usize) -> Vec<(Vec<Keysym>, Vec<usize>)> {
+    let mut segments: Vec<(Vec<Keysym>, Vec<usize>)> = Vec::new();
+    for keysym in keysyms {
+        let full = segments
+            .last()
+            .is_none_or(|(keys, _)| !keys.contains(keysym) && keys.len() >= max);
+        if full {
+            segments.push((Vec::new(), Vec::new()));
+        }
+        let Some((keys, indexes)) = segments.last_mut() else {
+            continue;
+        };
+        let index = match keys.iter().position(|key| key == keysym) {
+            Some(index) => index,
+            None => {
+                keys.push(*keysym);
+                keys.len() - 1
+            }
+        };
+        indexes.push(index);
+    }
+    segments
+}
+
+/// 临时创建的虚拟键盘，结束时销毁
+struct VirtualKeyboard {
+    conn: Connection,
+    queue: EventQueue<State>,
+    state: State,
+    keyboard: ZwpVirtualKeyboardV1,
+    /// 当前 keymap 的文件，合成器读取前需要保持打开
+    keymap: Option<File>,
+}
+
+impl VirtualKeyboard {
+    fn connect() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|e| e.to_string())?;
+        let mut queue = conn.new_event_queue();
+        let qh = queue.handle();
+        let mut state = State::default();
+
+        conn.display().get_registry(&qh, ());
+        queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+
+        let seat = state.seat.as_ref().ok_or("No wl_seat advertised")?;
+        let manager = state
+            .manager
+            .as_ref()
+            .ok_or("Compositor does not support zwp_virtual_keyboard_v1")?;
+        let keyboard = manager.create_virtual_keyboard(seat, &qh, ());
+        Ok(Self {
+            conn,
+            queue,
+            state,
+            keyboard,
+            keymap: None,
+        })
+    }
+
+    /// 上传只包含 keys 的 keymap，第 i 个键的键码为 i + 1
+    fn set_keymap(&mut self, keys: &[Keysym]) -> Result<(), String> {
+        let keymap = keymap(keys);
+        let file = keymap_file(&keymap)?;
+        self.keyboard.keymap(
+            wl_keyboard::KeymapFormat::XkbV1.into(),
+            file.as_fd(),
+            keymap.len() as u32 + 1,
+        );
+        self.keymap = Some(file);
+        Ok(())
+    }
+
+    fn key(&self, index: usize, state: wl_keyboard::KeyState) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u32);
+        self.keyboard.key(time, index as u32 + 1, state.into());
+    }
+
+    /// 往返一次确认合成器已处理，未授权等协议错误也在这里返回
+    fn finish(mut self) -> Result<(), String> {
+        let result = self
+            .queue
+            .roundtrip(&mut self.state)
+            .map_err(|e| e.to_string());
+        self.keyboard.destroy();
+        let _ = self.conn.flush();
+        result.map(|_| ())
+    }
+}
+
+/// 修饰键在 keymap 中的名称和对应的掩码
+fn modifier(keysym: Keysym) -> Option<(&'static str, u32)> {
+    match keysym {
+        0xffe1 | 0xffe2 => Some(("Shift", 1 << 0)),
+        0xffe3 | 0xffe4 => Some(("Control", 1 << 2)),
+        0xffe9 | 0xffea => Some(("Mod1", 1 << 3)),
+        0xffeb | 0xffec => Some(("Mod4", 1 << 6)),
+        _ => None,
+    }
+}
+
+/// 为每个 keysym 分配一个键码（evdev 键码 + 8）的最小 XKB keymap
+fn keymap(keys: &[Keysym]) -> String {
+    let mut keycodes = String::new();
+    let mut symbols = String::new();
+    for (index, key) in keys.iter().enumerate() {
+        keycodes.push_str(&format!("        <K{index}> = {};\n", index + 9));
+        symbols.push_str(&format!("        key <K{index}> {{ [ {key:#x} ] }};\n"));
+        if let Some((name, _)) = modifier(*key) {
+            symbols.push_str(&format!("        modifier_map {name} {{ <K{index}> }};\n"));
+        }
+    }
+    format!(
+        "xkb_keymap {{\n    xkb_keycodes \"super-paste\" {{\n        minimum = 8;\n        maximum = {};\n{keycodes}    }};\n    xkb_types \"super-paste\" {{ include \"complete\" }};\n    xkb_compat \"super-paste\" {{ include \"complete\" }};\n    xkb_symbols \"super-paste\" {{\n{symbols}    }};\n}};\n",
+        keys.len() + 9
+    )
+}
+
+/// 合成器通过 mmap 读取 keymap，末尾需要 NUL
+fn keymap_file(keymap: &str) -> Result<File, String> {
+    let fd = unsafe { libc::memfd_create(c"super-paste-keymap".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    file.write_all(keymap.as_bytes())
+        .and_then(|_| file.write_all(&[0]))
+        .map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for State {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            match interface.as_str() {
+                "wl_seat" if state.seat.is_none() => {
+                    state.seat = Some(registry.bind(name, version.min(2), qh, ()));
+                }
+                "zwp_virtual_keyboard_manager_v1" => {
+                    state.manager = Some(registry.bind(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+delegate_noop!(State: ignore wl_seat::WlSeat);
+delegate_noop!(State: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(State: ZwpVirtualKeyboardV1);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keysym;
+
+    #[test]
+    fn segments_start_over_when_the_keymap_is_full() {
+        let segments = segments(&[1, 2, 1, 3, 2, 3], 2);
+        assert_eq!(
+            segments,
+            vec![(vec![1, 2], vec![0, 1, 0]), (vec![3, 2], vec![0, 1, 0])]
+        );
+    }
+
+    #[test]
+    fn keymap_maps_each_key_and_its_modifier() {
+        let keymap = keymap(&[keysym::CONTROL_L, keysym::V]);
+        assert!(keymap.contains("<K0> = 9;"));
+        assert!(keymap.contains("key <K0> { [ 0xffe3 ] };"));
+        assert!(keymap.contains("modifier_map Control { <K0> };"));
+        assert!(keymap.contains("key <K1> { [ 0x76 ] };"));
+        assert!(keymap.contains("maximum = 11;"));
+    }
+
+    /// 需要支持虚拟键盘协议的合成器，例如：
+    /// `WLR_BACKENDS=headless WLR_LIBINPUT_NO_DEVICES=1 sway &`
+    /// `WAYLAND_DISPLAY=wayland-1 cargo test -- --ignored`
+    #[test]
+    #[ignore = "requires a Wayland compositor with zwp_virtual_keyboard_v1"]
+    fn compositor_accepts_the_paste_chord() {
+        send_chord(&[keysym::CONTROL_L, keysym::V]).unwrap();
+    }
+}
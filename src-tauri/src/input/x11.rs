@@ -0,0 +1,238 @@
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    ConnectionExt as _, Keycode, Window, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, KEY_PRESS_EVENT,
+    KEY_RELEASE_EVENT,
+};
+use x11rb::protocol::xtest::{self, ConnectionExt as _};
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::CURRENT_TIME;
+
+use std::thread;
+use std::time::Duration;
+
+use super::keysym::{self, Keysym};
+use super::Typing;
+
+/// 依次按下各键再逆序松开，作为一次组合键发给当前焦点窗口
+pub fn send_chord(keys: &[Keysym]) -> Result<(), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    press_chord(&conn, root, keys)
+}
+
+/// 在指针当前位置点击鼠标中键，粘贴 PRIMARY 选区
+pub fn click_middle() -> Result<(), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    fake_input(
+        &conn,
+        root,
+        &[(BUTTON_PRESS_EVENT, 2), (BUTTON_RELEASE_EVENT, 2)],
+    )
+}
+
+fn press_chord(conn: &impl Connection, root: Window, keys: &[Keysym]) -> Result<(), String> {
+    let keycodes = keys
+        .iter()
+        .map(|keysym| {
+            keycode_for(conn, *keysym)
+                .ok_or_else(|| format!("No key on the current layout produces keysym {keysym:#x}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let events = keycodes
+        .iter()
+        .map(|keycode| (KEY_PRESS_EVENT, *keycode))
+        .chain(
+            keycodes
+                .iter()
+                .rev()
+                .map(|keycode| (KEY_RELEASE_EVENT, *keycode)),
+        )
+        .collect::<Vec<_>>();
+    fake_input(conn, root, &events)
+}
+
+/// 通过 XTEST 依次发送 (事件类型, 键码或按钮) 事件
+fn fake_input(conn: &impl Connection, root: Window, events: &[(u8, u8)]) -> Result<(), String> {
+    if conn
+        .extension_information(xtest::X11_EXTENSION_NAME)
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err("X server does not support the XTEST extension".to_string());
+    }
+    for (type_, detail) in events {
+        conn.xtest_fake_input(*type_, *detail, CURRENT_TIME, root, 0, 0, 0)
+            .map_err(|e| e.to_string())?;
+    }
+    // 等服务器处理完再返回，调用方随后可能立即恢复剪贴板或切换焦点
+    conn.sync().map_err(|e| e.to_string())
+}
+
+/// 在当前键盘映射中查找能产生该 keysym 的键，优先不需要 Shift 的位置
+fn keycode_for(conn: &impl Connection, keysym: Keysym) -> Option<Keycode> {
+    KeyboardMapping::read(conn)?
+        .find(keysym)
+        .map(|(keycode, _)| keycode)
+}
+
+/// 逐字符输入。当前布局中没有的字符临时映射到一个空闲键码上，输入完再清空
+pub fn type_text(typing: &Typing) -> Result<(), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    let mapping = KeyboardMapping::read(&conn).ok_or("Cannot read the keyboard mapping")?;
+    let (shift, _) = mapping
+        .find(keysym::SHIFT_L)
+        .ok_or("No key on the current layout produces Shift")?;
+    let spare = mapping.spare();
+    let mut remapped = None;
+
+    let mut result = Ok(());
+    for keysym in typing.keysyms() {
+        if !typing.next() {
+            break;
+        }
+        let tap = |keycode| [(KEY_PRESS_EVENT, keycode), (KEY_RELEASE_EVENT, keycode)];
+        result = match mapping.find(keysym) {
+            Some((keycode, 0)) => fake_input(&conn, root, &tap(keycode)),
+            Some((keycode, 1)) => fake_input(
+                &conn,
+                root,
+                &[
+                    (KEY_PRESS_EVENT, shift),
+                    (KEY_PRESS_EVENT, keycode),
+                    (KEY_RELEASE_EVENT, keycode),
+                    (KEY_RELEASE_EVENT, shift),
+                ],
+            ),
+            _ => match spare {
+                Some(spare) => {
+                    let remap = if remapped == Some(keysym) {
+                        Ok(())
+                    } else {
+                        remapped = Some(keysym);
+                        mapping.remap(&conn, spare, keysym)
+                    };
+                    remap.and_then(|_| fake_input(&conn, root, &tap(spare)))
+                }
+                None => Err(format!("No spare keycode to type keysym {keysym:#x}")),
+            },
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+
+    if let (Some(spare), Some(_)) = (spare, remapped) {
+        // 留出时间让目标窗口按临时映射处理完最后一个键
+        thread::sleep(Duration::from_millis(50));
+        let _ = mapping.remap(&conn, spare, x11rb::NONE);
+    }
+    result
+}
+
+/// 服务器的键码到 keysym 映射表
+struct KeyboardMapping {
+    min: Keycode,
+    per_keycode: usize,
+    keysyms: Vec<Keysym>,
+}
+
+impl KeyboardMapping {
+    fn read(conn: &impl Connection) -> Option<Self> {
+        let setup = conn.setup();
+        let (min, max) = (setup.min_keycode, setup.max_keycode);
+        let mapping = conn
+            .get_keyboard_mapping(min, max - min + 1)
+            .ok()?
+            .reply()
+            .ok()?;
+        let per_keycode = mapping.keysyms_per_keycode as usize;
+        (per_keycode > 0).then_some(Self {
+            min,
+            per_keycode,
+            keysyms: mapping.keysyms,
+        })
+    }
+
+    /// 能产生该 keysym 的键码及所在列，列 1 需要按住 Shift
+    fn find(&self, keysym: Keysym) -> Option<(Keycode, usize)> {
+        (0..self.per_keycode).find_map(|column| {
+            self.keysyms
+                .chunks(self.per_keycode)
+                .position(|syms| syms.get(column) == Some(&keysym))
+                .map(|index| (self.min + index as u8, column))
+        })
+    }
+
+    /// 没有映射任何 keysym 的键码
+    fn spare(&self) -> Option<Keycode> {
+        self.keysyms
+            .chunks(self.per_keycode)
+            .rposition(|syms| syms.iter().all(|sym| *sym == x11rb::NONE))
+            .map(|index| self.min + index as u8)
+    }
+
+    /// 把键码的所有列都映射为 keysym，keysym 为 0 时清空
+    fn remap(
+        &self,
+        conn: &impl Connection,
+        keycode: Keycode,
+        keysym: Keysym,
+    ) -> Result<(), String> {
+        let keysyms = vec![keysym; self.per_keycode];
+        conn.change_keyboard_mapping(1, keycode, self.per_keycode as u8, &keysyms)
+            .map_err(|e| e.to_string())?;
+        conn.sync().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::focus;
+    use crate::input::keysym;
+    use x11rb::protocol::xproto::{CreateWindowAux, EventMask, KeyButMask, WindowClass};
+    use x11rb::protocol::Event;
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    /// 需要支持 XTEST 的 X 服务器，例如：
+    /// `Xvfb :99 & DISPLAY=:99 cargo test -- --ignored`
+    #[test]
+    #[ignore = "requires an X server with the XTEST extension"]
+    fn paste_chord_reaches_the_focused_window() {
+        let (conn, screen_num) = x11rb::connect(None).expect("connect to X server");
+        let screen = &conn.setup().roots[screen_num];
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            screen.root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new().event_mask(EventMask::KEY_PRESS),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.sync().unwrap();
+        assert!(focus::x11_activate_window(&conn, screen.root, window));
+
+        send_chord(&[keysym::CONTROL_L, keysym::V]).unwrap();
+
+        let v = keycode_for(&conn, keysym::V).unwrap();
+        loop {
+            if let Event::KeyPress(event) = conn.wait_for_event().unwrap() {
+                if event.detail == v {
+                    assert!(event.state.contains(KeyButMask::CONTROL));
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -1,12 +1,27 @@
+mod budget;
 mod clipboard;
 mod commands;
+mod crypto;
 mod focus;
+mod input;
+mod maintenance;
 mod models;
+mod retention;
+mod rules;
+mod settings;
+mod shortcut;
+mod storage;
 mod tray;
 
-use clipboard::ClipboardWatcher;
+use clipboard::{ClipboardWatcher, OwnWrites};
+use crypto::EncryptionState;
+use input::TypingState;
+use rules::AppRulesState;
+use settings::SettingsState;
+use shortcut::ShortcutManager;
+use storage::HistoryStore;
 use tauri::Manager;
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri_plugin_global_shortcut::ShortcutState;
 
 #[cfg(target_os = "macos")]
 use tauri_nspanel::{
@@ -141,11 +156,50 @@ fn handle_shortcut(app: &tauri::AppHandle) {
     }
 }
 
+/// sp-image 协议：读取 images 目录下的图片，加密过的图片解密后返回
+fn serve_image<R: tauri::Runtime>(
+    ctx: tauri::UriSchemeContext<'_, R>,
+    request: tauri::http::Request<Vec<u8>>,
+) -> tauri::http::Response<Vec<u8>> {
+    let respond = |status: u16, body: Vec<u8>| {
+        tauri::http::Response::builder()
+            .status(status)
+            .header("Content-Type", "image/png")
+            .body(body)
+            .unwrap_or_default()
+    };
+
+    let app = ctx.app_handle();
+    let path = percent_encoding::percent_decode_str(request.uri().path().trim_start_matches('/'))
+        .decode_utf8_lossy()
+        .into_owned();
+    // 只允许读取本应用保存的图片
+    let allowed = match (
+        app.path()
+            .app_data_dir()
+            .and_then(|dir| Ok(dir.join("images").canonicalize()?)),
+        std::path::Path::new(&path).canonicalize(),
+    ) {
+        (Ok(images_dir), Ok(path)) => path.starts_with(images_dir),
+        _ => false,
+    };
+    if !allowed {
+        return respond(403, Vec::new());
+    }
+
+    match app
+        .state::<HistoryStore>()
+        .read_image(std::path::Path::new(&path))
+    {
+        Ok(bytes) => respond(200, bytes),
+        Err(_) => respond(404, Vec::new()),
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let mut builder = tauri::Builder::default()
-        .plugin(tauri_plugin_clipboard_manager::init())
-        .plugin(tauri_plugin_sql::Builder::default().build());
+        .plugin(tauri_plugin_clipboard_manager::init());
 
     #[cfg(target_os = "macos")]
     {
@@ -155,13 +209,18 @@ pub fn run() {
     builder
         .plugin(
             tauri_plugin_global_shortcut::Builder::new()
-                .with_handler(|app, _shortcut, event| {
+                .with_handler(|app, shortcut, event| {
                     if event.state() == ShortcutState::Pressed {
-                        handle_shortcut(app);
+                        // 逐字输入期间额外注册的中止快捷键
+                        match app.try_state::<TypingState>() {
+                            Some(typing) if typing.is_abort_shortcut(shortcut) => typing.abort(),
+                            _ => handle_shortcut(app),
+                        }
                     }
                 })
                 .build(),
         )
+        .register_uri_scheme_protocol("sp-image", serve_image)
         .setup(|app| {
             // Hide from dock on macOS
             #[cfg(target_os = "macos")]
@@ -204,25 +263,57 @@ pub fn run() {
             // Create system tray
             tray::create_tray(app)?;
 
-            // Start clipboard watcher
+            // Open clipboard history store
+            let config_dir = app.path().app_config_dir()?;
+            std::fs::create_dir_all(&config_dir)?;
+            let store = HistoryStore::open(&config_dir.join(storage::DB_FILE))?;
+            let settings = SettingsState::load(&store);
+            let accelerator = settings.get().shortcut;
+            app.manage(settings);
+            app.manage(AppRulesState::load(&store));
+            app.manage(EncryptionState::load(&config_dir, &store));
+            app.manage(store);
+
+            // Start clipboard watcher; writes made by copy_to_clipboard are skipped
+            app.manage(OwnWrites::default());
             let watcher = ClipboardWatcher::new();
             watcher.start(app.handle().clone());
 
-            // Register global shortcut
-            app.global_shortcut()
-                .register("CommandOrControl+Shift+V")
-                .expect("Failed to register shortcut");
+            // Prune expired and old history (and their image files) now and periodically
+            retention::start(app.handle().clone());
+
+            // Register the persisted global shortcut; a conflict is reported, not fatal
+            let shortcuts = ShortcutManager::new();
+            if let Err(e) = shortcuts.rebind(app.handle(), &accelerator) {
+                eprintln!("Failed to register shortcut: {}", e);
+            }
+            app.manage(shortcuts);
+            app.manage(TypingState::default());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_settings,
             commands::save_settings,
+            commands::get_shortcut_status,
             commands::hide_window,
             commands::show_window,
             commands::copy_to_clipboard,
-            commands::delete_image_files,
+            commands::list_items,
+            commands::search_items,
+            commands::get_item,
+            commands::delete_item,
+            commands::touch_item,
+            commands::set_pinned,
+            commands::run_maintenance,
             commands::paste,
+            commands::list_app_rules,
+            commands::save_app_rule,
+            commands::delete_app_rule,
+            commands::get_encryption_status,
+            commands::unlock_history,
+            commands::enable_encryption,
+            commands::disable_encryption,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,232 @@
+use crate::clipboard;
+use crate::models::MaintenanceReport;
+use crate::storage::HistoryStore;
+use image::DynamicImage;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// 最近写入的文件可能属于尚未入库的捕获，清理时跳过
+const ORPHAN_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// 对照数据库检查 images 目录：删除没有记录引用的文件，从原图重新生成丢失的缩略图，
+/// 标记原图已丢失或内容与 hash 不符的图片条目（原图恢复后取消标记）
+pub fn run(store: &HistoryStore, images_dir: &Path) -> Result<MaintenanceReport, String> {
+    let mut report = MaintenanceReport::default();
+    let mut referenced = HashSet::new();
+
+    for refs in store.file_refs().map_err(|e| e.to_string())? {
+        let original = refs
+            .image_path
+            .as_deref()
+            .map(Path::new)
+            .filter(|path| path.exists());
+        let mut decoded = None;
+        if refs.content_type == "image" {
+            if let Some(path) = original {
+                match load_image(store, path) {
+                    // 以内容 hash 命名的原图重新计算 hash，与记录不符视为损坏。
+                    // 开启加密时记录和文件名都是带密钥的 hash
+                    Ok(img)
+                        if path.file_stem() == Some(refs.content_hash.as_ref())
+                            && store
+                                .content_key(&clipboard::compute_hash(img.to_rgba8().as_raw()))
+                                != refs.content_hash =>
+                    {
+                        report.corrupt_originals += 1
+                    }
+                    Ok(img) => decoded = Some(img),
+                    Err(_) => report.corrupt_originals += 1,
+                }
+            }
+            let intact = decoded.is_some();
+            if original.is_none() {
+                report.missing_originals += 1;
+            }
+            if refs.missing == intact {
+                store
+                    .set_missing(refs.id, !intact)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        let mut thumbnail = refs.thumbnail_path.clone();
+        if thumbnail.as_deref().is_none_or(|p| !Path::new(p).exists()) {
+            thumbnail = match (original, decoded) {
+                (Some(original), Some(img)) => {
+                    match save_thumbnail(store, images_dir, original, img) {
+                        Ok(path) => {
+                            report.thumbnails_regenerated += 1;
+                            Some(path)
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to regenerate thumbnail for {}: {}", refs.id, e);
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            if thumbnail.is_none() && refs.thumbnail_path.is_some() {
+                report.thumbnails_dropped += 1;
+            }
+            if thumbnail != refs.thumbnail_path {
+                store
+                    .set_thumbnail(refs.id, thumbnail.as_deref())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        referenced.extend(refs.image_path.map(PathBuf::from));
+        referenced.extend(thumbnail.map(PathBuf::from));
+    }
+
+    let entries = match fs::read_dir(images_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e.to_string()),
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        let recent = meta
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_none_or(|age| age < ORPHAN_GRACE);
+        if !meta.is_file() || recent || referenced.contains(&path) {
+            continue;
+        }
+        if fs::remove_file(&path).is_ok() {
+            report.orphans_removed += 1;
+            report.freed_bytes += meta.len();
+        }
+    }
+    Ok(report)
+}
+
+/// 读取原图，加密过的文件先解密
+fn load_image(store: &HistoryStore, path: &Path) -> Result<DynamicImage, String> {
+    let bytes = store.read_image(path)?;
+    image::load_from_memory(&bytes).map_err(|e| e.to_string())
+}
+
+/// 缩略图按原图文件名派生，开启加密时同样加密保存
+fn save_thumbnail(
+    store: &HistoryStore,
+    images_dir: &Path,
+    original: &Path,
+    img: DynamicImage,
+) -> Result<String, String> {
+    let key = original
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or("Invalid image file name")?;
+    let path = clipboard::save_thumbnail(images_dir, key, img)?;
+    store.protect_file(Path::new(&path))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ClipboardPayload;
+    use image::{Rgba, RgbaImage};
+    use std::fs::File;
+
+    /// 按内容 hash 保存一张纯色图片并入库
+    fn add_image(store: &HistoryStore, dir: &Path, shade: u8) -> (i64, PathBuf, PathBuf) {
+        let img = RgbaImage::from_pixel(400, 300, Rgba([shade, shade, shade, 255]));
+        let hash = clipboard::compute_hash(img.as_raw());
+        let image_path = dir.join(format!("{hash}.png"));
+        let thumbnail_path = dir.join(format!("{hash}_thumb.png"));
+        img.save(&image_path).unwrap();
+        fs::write(&thumbnail_path, b"thumb").unwrap();
+        let item = store
+            .upsert(&ClipboardPayload {
+                content_type: "image".to_string(),
+                content_hash: hash,
+                image_path: Some(image_path.to_string_lossy().to_string()),
+                thumbnail_path: Some(thumbnail_path.to_string_lossy().to_string()),
+                file_size: 400 * 300 * 4,
+                ..Default::default()
+            })
+            .unwrap();
+        (item.id, image_path, thumbnail_path)
+    }
+
+    /// 写入一个修改时间早于宽限期的文件
+    fn write_old(path: &Path) {
+        fs::write(path, b"orphan").unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(SystemTime::now() - ORPHAN_GRACE * 2)
+            .unwrap();
+    }
+
+    #[test]
+    fn removes_old_orphans_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let (_, image_path, thumbnail_path) = add_image(&store, dir.path(), 1);
+        let orphan = dir.path().join("orphan.png");
+        write_old(&orphan);
+        let fresh = dir.path().join("fresh.png");
+        fs::write(&fresh, b"in flight").unwrap();
+
+        let report = run(&store, dir.path()).unwrap();
+        assert_eq!(report.orphans_removed, 1);
+        assert_eq!(report.freed_bytes, 6);
+        assert!(!orphan.exists());
+        assert!(fresh.exists());
+        assert!(image_path.exists() && thumbnail_path.exists());
+    }
+
+    #[test]
+    fn regenerates_missing_thumbnails_and_marks_missing_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let (repaired, _, thumbnail_path) = add_image(&store, dir.path(), 2);
+        fs::remove_file(&thumbnail_path).unwrap();
+        let (lost, image_path, _) = add_image(&store, dir.path(), 3);
+        fs::remove_file(&image_path).unwrap();
+
+        let report = run(&store, dir.path()).unwrap();
+        assert_eq!(report.thumbnails_regenerated, 1);
+        assert_eq!(report.missing_originals, 1);
+
+        let item = store.get(repaired).unwrap().unwrap();
+        assert!(!item.missing);
+        // 缩略图按原图文件名重新生成在原来的位置
+        assert_eq!(item.thumbnail_path.as_deref(), thumbnail_path.to_str());
+        let thumbnail = image::open(thumbnail_path).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (200, 150));
+        assert!(store.get(lost).unwrap().unwrap().missing);
+
+        // 再次运行没有需要修复的内容
+        let report = run(&store, dir.path()).unwrap();
+        assert_eq!(report.thumbnails_regenerated, 0);
+        assert_eq!(report.missing_originals, 1);
+    }
+
+    #[test]
+    fn originals_that_no_longer_match_their_hash_are_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::open_in_memory().unwrap();
+        let (id, image_path, _) = add_image(&store, dir.path(), 4);
+        RgbaImage::from_pixel(400, 300, Rgba([5, 5, 5, 255]))
+            .save(&image_path)
+            .unwrap();
+
+        let report = run(&store, dir.path()).unwrap();
+        assert_eq!(report.corrupt_originals, 1);
+        assert_eq!(report.missing_originals, 0);
+        assert!(store.get(id).unwrap().unwrap().missing);
+    }
+}
@@ -3,23 +3,222 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ClipboardItem {
     pub id: i64,
-    pub content_type: String, // "text" | "image"
+    pub content_type: String, // "text" | "image" | "files"
     pub content_hash: String, // SHA256 hash for dedup
     pub text_content: Option<String>,
     pub image_path: Option<String>,
     pub thumbnail_path: Option<String>,
     pub file_size: i64,
-    pub source_app: Option<String>,
+    pub source_app: Option<SourceApp>,
     pub created_at: String,
     pub updated_at: String,
+    /// 除纯文本/图片外额外保存的 MIME 类型，如 text/html
+    #[serde(default)]
+    pub formats: Vec<String>,
+    /// files 类型条目中的文件，记录的是捕获时的状态
+    #[serde(default)]
+    pub files: Vec<FileEntry>,
+    /// 按规则限时保留的条目在此时间后删除
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// 识别出的密钥类型，列表中此类条目的文本会被遮盖
+    #[serde(default)]
+    pub sensitive: Option<String>,
+    /// 固定的条目不会因图片空间超限被清理
+    #[serde(default)]
+    pub pinned: bool,
+    /// 图片条目的原图文件已不存在
+    #[serde(default)]
+    pub missing: bool,
+    /// 图片仍在后台写入，只出现在捕获时发出的事件中
+    #[serde(default)]
+    pub pending: bool,
+}
+
+/// 复制内容时所在的应用，各平台能拿到的字段不同
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SourceApp {
+    /// 应用标识：macOS 为 bundle id，Windows/Linux 为可执行文件名
+    pub id: Option<String>,
+    /// 显示名称
+    pub name: Option<String>,
+    /// X11 窗口的 WM_CLASS
+    pub window_class: Option<String>,
+    /// 窗口标题
+    pub title: Option<String>,
+    pub pid: Option<u32>,
+}
+
+/// 复制的单个文件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub size: i64,
+    pub exists: bool,
+    pub is_dir: bool,
+}
+
+/// 剪贴板内容的一种表示形式（如 text/html、text/rtf），与所属条目共用同一个 hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardFormat {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// 剪贴板监听器捕获到的新内容，写入历史存储前的形态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClipboardPayload {
+    pub content_type: String,
+    pub content_hash: String,
+    pub text_content: Option<String>,
+    pub image_path: Option<String>,
+    pub thumbnail_path: Option<String>,
+    pub file_size: i64,
+    #[serde(skip)]
+    pub formats: Vec<ClipboardFormat>,
+    pub files: Vec<FileEntry>,
+    pub source_app: Option<SourceApp>,
+    /// 命中限时保留规则或识别为密钥时的保留分钟数
+    pub keep_minutes: Option<u32>,
+    /// 识别出的密钥类型
+    pub sensitive: Option<String>,
+    /// 图片的 dHash，开启相似图片合并时才计算
+    pub perceptual_hash: Option<String>,
+}
+
+/// 没有记录的剪贴板内容，通过 clipboard-skipped 事件告知前端
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedCapture {
+    pub reason: String,
+    pub detail: Option<String>,
+    pub source_app: Option<SourceApp>,
+}
+
+/// 图片空间超限后清理的结果，通过 images-evicted 事件告知前端
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageEviction {
+    /// 被删除的条目 id，从旧到新
+    pub evicted: Vec<i64>,
+    pub freed_bytes: u64,
+    /// 清理后图片占用的空间
+    pub used_bytes: u64,
+    pub quota_bytes: u64,
+}
+
+/// 后台写完图片文件后通过 image-ready 事件告知前端，失败时条目已被删除
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageReady {
+    pub content_hash: String,
+    pub error: Option<String>,
+}
+
+/// 清理与修复的结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MaintenanceReport {
+    /// 删除的没有记录引用的文件
+    pub orphans_removed: usize,
+    pub freed_bytes: u64,
+    /// 从原图重新生成的缩略图
+    pub thumbnails_regenerated: usize,
+    /// 无法重新生成、已从记录中移除的缩略图
+    pub thumbnails_dropped: usize,
+    /// 原图文件已丢失的图片条目
+    pub missing_originals: usize,
+    /// 原图文件仍在但无法读取或内容与 hash 不符的条目
+    pub corrupt_originals: usize,
+}
+
+/// 按来源应用生效的捕获规则，填写的匹配条件需全部满足
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppRule {
+    /// 新建规则时为空
+    #[serde(default)]
+    pub id: Option<i64>,
+    /// 应用标识或显示名称，不区分大小写
+    pub app_id: Option<String>,
+    /// X11 窗口的 WM_CLASS，不区分大小写
+    pub window_class: Option<String>,
+    /// 匹配窗口标题的正则表达式
+    pub title_pattern: Option<String>,
+    pub action: RuleAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 命中规则后对捕获内容的处理
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// 不记录
+    Ignore,
+    /// 只保留指定分钟数
+    KeepMinutes { minutes: u32 },
+    /// 只记录纯文本，丢弃图片、文件和富文本格式
+    TextOnly,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub shortcut: String,
     pub max_items: i32,
     pub max_days: i32,
     pub max_file_size_mb: i32,
+    /// 从历史中粘贴时是否把该条目移到最前
+    pub move_to_top_on_paste: bool,
+    /// 是否识别复制的密钥、令牌和卡号
+    pub detect_secrets: bool,
+    /// 识别为密钥的条目保留的分钟数
+    pub secret_ttl_minutes: i32,
+    /// 识别密钥使用的正则
+    pub secret_patterns: Vec<SecretPattern>,
+    /// 图片占用的总空间上限，超出时清理最旧的未固定图片
+    pub max_image_storage_mb: i32,
+    /// 是否把看起来相同的图片合并到已有条目
+    pub merge_similar_images: bool,
+    /// 视为相同图片时 dHash 允许不同的位数
+    pub similar_image_distance: i32,
+    /// 按目标应用选择的粘贴方式，未命中时使用 Ctrl+V
+    pub paste_chords: Vec<PasteChordRule>,
+    /// 逐字输入时每个字符之间的间隔
+    pub type_delay_ms: i32,
+    /// 逐字输入过程中用于中止的全局快捷键
+    pub type_abort_shortcut: String,
+    /// 粘贴后恢复粘贴前的剪贴板内容，历史条目不留在剪贴板中。中键粘贴时不生效
+    pub restore_clipboard_after_paste: bool,
+}
+
+/// 命名的密钥正则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecretPattern {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// 粘贴到目标应用时模拟的操作，macOS 始终使用 Cmd+V
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteChord {
+    CtrlV,
+    /// 多数终端的粘贴快捷键
+    CtrlShiftV,
+    ShiftInsert,
+    /// 同时写入 PRIMARY 选区，再点击鼠标中键，仅 Linux 支持
+    MiddleClick,
+    /// 把文本逐字模拟为按键输入，用于不接受粘贴的虚拟机控制台、网页终端等
+    TypeText,
+}
+
+/// 目标应用使用的粘贴方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasteChordRule {
+    /// 窗口类名、进程名或应用名，不区分大小写
+    pub app: String,
+    pub chord: PasteChord,
 }
 
 impl Default for Settings {
@@ -29,6 +228,17 @@ impl Default for Settings {
             max_items: 1000,
             max_days: 30,
             max_file_size_mb: 10,
+            move_to_top_on_paste: true,
+            detect_secrets: true,
+            secret_ttl_minutes: 5,
+            secret_patterns: crate::clipboard::default_secret_patterns(),
+            max_image_storage_mb: 1024,
+            merge_similar_images: false,
+            similar_image_distance: 4,
+            paste_chords: crate::input::default_paste_chords(),
+            type_delay_ms: 10,
+            type_abort_shortcut: "Escape".to_string(),
+            restore_clipboard_after_paste: false,
         }
     }
 }
@@ -0,0 +1,163 @@
+use crate::budget;
+use crate::settings::SettingsState;
+use crate::storage::HistoryStore;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// 定期清理的间隔，限时保留的条目最短只保留 1 分钟
+const INTERVAL: Duration = Duration::from_secs(60);
+
+/// 启动时立即清理一次，之后按固定间隔在后台清理
+pub fn start(app: AppHandle) {
+    thread::spawn(move || loop {
+        run(&app);
+        thread::sleep(INTERVAL);
+    });
+}
+
+/// 按当前设置清理过期、超出条数或天数限制的记录及其图片，再检查图片空间上限。
+/// 有记录被删除时发出 history-pruned 事件
+pub fn run(app: &AppHandle) {
+    let settings = app.state::<SettingsState>().get();
+    let store = app.state::<HistoryStore>();
+    match store.apply_retention(settings.max_items as u32, settings.max_days as u32) {
+        Ok(0) => {}
+        Ok(removed) => {
+            let _ = app.emit("history-pruned", removed);
+        }
+        Err(e) => eprintln!("Failed to apply retention: {}", e),
+    }
+    budget::enforce_and_report(app, &store, settings.max_image_storage_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ClipboardPayload;
+    use crate::storage::HistoryStore;
+    use rusqlite::Connection;
+    use std::fs;
+    use std::path::Path;
+
+    fn payload(hash: &str) -> ClipboardPayload {
+        ClipboardPayload {
+            content_type: "text".to_string(),
+            content_hash: hash.to_string(),
+            text_content: Some(hash.to_string()),
+            file_size: hash.len() as i64,
+            ..Default::default()
+        }
+    }
+
+    fn image(dir: &Path, hash: &str) -> ClipboardPayload {
+        let image_path = dir.join(format!("{hash}.png"));
+        let thumbnail_path = dir.join(format!("{hash}_thumb.png"));
+        fs::write(&image_path, b"png").unwrap();
+        fs::write(&thumbnail_path, b"thumb").unwrap();
+        ClipboardPayload {
+            content_type: "image".to_string(),
+            image_path: Some(image_path.to_string_lossy().to_string()),
+            thumbnail_path: Some(thumbnail_path.to_string_lossy().to_string()),
+            text_content: None,
+            ..payload(hash)
+        }
+    }
+
+    /// 把条目的更新时间改到 days 天前
+    fn age(db_path: &Path, id: i64, days: u32) {
+        Connection::open(db_path)
+            .unwrap()
+            .execute(
+                "UPDATE clipboard_items SET updated_at = datetime('now', ?2) WHERE id = ?1",
+                rusqlite::params![id, format!("-{days} days")],
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn old_items_are_removed_with_their_image_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let old = store.upsert(&image(dir.path(), "old")).unwrap();
+        let recent = store.upsert(&image(dir.path(), "recent")).unwrap();
+        age(&db_path, old.id, 40);
+
+        assert_eq!(store.apply_retention(1000, 30).unwrap(), 1);
+        assert!(store.get(old.id).unwrap().is_none());
+        assert!(!dir.path().join("old.png").exists());
+        assert!(!dir.path().join("old_thumb.png").exists());
+        assert!(store.get(recent.id).unwrap().is_some());
+        assert!(dir.path().join("recent.png").exists());
+    }
+
+    #[test]
+    fn shared_thumbnails_are_kept_while_still_referenced() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let shared = dir.path().join("shared_thumb.png");
+        fs::write(&shared, b"thumb").unwrap();
+        let files = |hash: &str| ClipboardPayload {
+            content_type: "files".to_string(),
+            thumbnail_path: Some(shared.to_string_lossy().to_string()),
+            ..payload(hash)
+        };
+        let old = store.upsert(&files("old")).unwrap();
+        let recent = store.upsert(&files("recent")).unwrap();
+        age(&db_path, old.id, 40);
+
+        assert_eq!(store.apply_retention(1000, 30).unwrap(), 1);
+        assert!(shared.exists());
+
+        // 最后一个引用被删除后文件随之删除
+        age(&db_path, recent.id, 40);
+        assert_eq!(store.apply_retention(1000, 30).unwrap(), 1);
+        assert!(!shared.exists());
+    }
+
+    #[test]
+    fn item_limit_keeps_the_newest_and_pinned_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let ids: Vec<i64> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|hash| store.upsert(&payload(hash)).unwrap().id)
+            .collect();
+        for (days, id) in ids.iter().rev().enumerate() {
+            age(&db_path, *id, days as u32);
+        }
+        store.set_pinned(ids[0], true).unwrap();
+        age(&db_path, ids[0], 100);
+
+        assert_eq!(store.apply_retention(2, 30).unwrap(), 1);
+        let remaining: Vec<i64> = store.list(10, None).unwrap().iter().map(|i| i.id).collect();
+        assert_eq!(remaining, vec![ids[3], ids[2], ids[0]]);
+    }
+
+    #[test]
+    fn expired_items_are_removed_even_when_pinned() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("history.db");
+        let store = HistoryStore::open(&db_path).unwrap();
+        let item = store
+            .upsert(&ClipboardPayload {
+                keep_minutes: Some(5),
+                ..payload("token")
+            })
+            .unwrap();
+        store.set_pinned(item.id, true).unwrap();
+        assert_eq!(store.apply_retention(1000, 30).unwrap(), 0);
+
+        Connection::open(&db_path)
+            .unwrap()
+            .execute(
+                "UPDATE clipboard_items SET expires_at = datetime('now', '-1 minutes')",
+                [],
+            )
+            .unwrap();
+        assert_eq!(store.apply_retention(1000, 30).unwrap(), 1);
+        assert!(store.get(item.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,248 @@
+use crate::models::{AppRule, RuleAction, SourceApp};
+use crate::storage::HistoryStore;
+use regex::Regex;
+use std::sync::RwLock;
+
+/// 运行时生效的应用规则，启动时从数据库加载，增删改后整体重新加载
+#[derive(Default)]
+pub struct AppRulesState {
+    rules: RwLock<Vec<CompiledRule>>,
+}
+
+/// 预编译标题正则的规则
+struct CompiledRule {
+    rule: AppRule,
+    title: Option<Regex>,
+}
+
+impl AppRulesState {
+    pub fn load(store: &HistoryStore) -> Self {
+        let state = Self::default();
+        match store.load_rules() {
+            Ok(rules) => state.set(rules),
+            Err(e) => eprintln!("Failed to load app rules: {}", e),
+        }
+        state
+    }
+
+    pub fn get(&self) -> Vec<AppRule> {
+        self.rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .map(|compiled| compiled.rule.clone())
+            .collect()
+    }
+
+    /// 替换全部规则，正则无法编译的规则跳过
+    pub fn set(&self, rules: Vec<AppRule>) {
+        let compiled = rules
+            .into_iter()
+            .filter_map(|rule| match rule.compile() {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    eprintln!("Skipping invalid app rule {:?}: {}", rule.id, e);
+                    None
+                }
+            })
+            .collect();
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = compiled;
+    }
+
+    /// 返回第一条命中来源应用的已启用规则的动作
+    pub fn action_for(&self, app: Option<&SourceApp>) -> Option<RuleAction> {
+        let app = app?;
+        self.rules
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|compiled| compiled.rule.enabled && compiled.matches(app))
+            .map(|compiled| compiled.rule.action)
+    }
+}
+
+impl AppRule {
+    /// 校验规则：至少填写一个匹配条件，标题正则可以编译，保留时间大于 0
+    pub fn validate(&self) -> Result<(), String> {
+        if [&self.app_id, &self.window_class, &self.title_pattern]
+            .iter()
+            .all(|field| is_blank(field))
+        {
+            return Err("规则至少需要填写应用、窗口类名或标题中的一项".to_string());
+        }
+        if let RuleAction::KeepMinutes { minutes } = self.action {
+            if minutes == 0 {
+                return Err("保留时间需大于 0 分钟".to_string());
+            }
+        }
+        self.compile().map(|_| ())
+    }
+
+    fn compile(&self) -> Result<CompiledRule, String> {
+        let title = match self.title_pattern.as_deref().map(str::trim) {
+            Some(pattern) if !pattern.is_empty() => {
+                Some(Regex::new(pattern).map_err(|e| format!("标题正则无效：{}", e))?)
+            }
+            _ => None,
+        };
+        Ok(CompiledRule {
+            rule: self.clone(),
+            title,
+        })
+    }
+}
+
+impl CompiledRule {
+    fn matches(&self, app: &SourceApp) -> bool {
+        if let Some(app_id) = non_blank(&self.rule.app_id) {
+            let matched = [&app.id, &app.name].iter().any(|value| {
+                value
+                    .as_deref()
+                    .is_some_and(|v| v.eq_ignore_ascii_case(app_id))
+            });
+            if !matched {
+                return false;
+            }
+        }
+        if let Some(class) = non_blank(&self.rule.window_class) {
+            if !app
+                .window_class
+                .as_deref()
+                .is_some_and(|v| v.eq_ignore_ascii_case(class))
+            {
+                return false;
+            }
+        }
+        if let Some(title) = &self.title {
+            if !app.title.as_deref().is_some_and(|t| title.is_match(t)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn non_blank(field: &Option<String>) -> Option<&str> {
+    field.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+fn is_blank(field: &Option<String>) -> bool {
+    non_blank(field).is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(app_id: Option<&str>, title: Option<&str>, action: RuleAction) -> AppRule {
+        AppRule {
+            id: None,
+            app_id: app_id.map(str::to_string),
+            window_class: None,
+            title_pattern: title.map(str::to_string),
+            action,
+            enabled: true,
+        }
+    }
+
+    fn app(id: &str, title: &str) -> SourceApp {
+        SourceApp {
+            id: Some(id.to_string()),
+            title: Some(title.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let state = AppRulesState::default();
+        state.set(vec![
+            rule(Some("KeePassXC"), None, RuleAction::Ignore),
+            rule(
+                None,
+                Some("(?i)terminal"),
+                RuleAction::KeepMinutes { minutes: 5 },
+            ),
+            rule(Some("keepassxc"), None, RuleAction::TextOnly),
+        ]);
+
+        assert_eq!(
+            state.action_for(Some(&app("keepassxc", "Passwords"))),
+            Some(RuleAction::Ignore)
+        );
+        assert_eq!(
+            state.action_for(Some(&app("gnome-terminal", "Terminal - bash"))),
+            Some(RuleAction::KeepMinutes { minutes: 5 })
+        );
+        assert_eq!(state.action_for(Some(&app("firefox", "Docs"))), None);
+        assert_eq!(state.action_for(None), None);
+    }
+
+    #[test]
+    fn all_conditions_must_match() {
+        let state = AppRulesState::default();
+        let mut rule = rule(Some("firefox"), Some("^Remote"), RuleAction::Ignore);
+        rule.window_class = Some("Navigator".to_string());
+        state.set(vec![rule]);
+
+        let mut matching = app("firefox", "Remote Desktop");
+        matching.window_class = Some("navigator".to_string());
+        assert_eq!(state.action_for(Some(&matching)), Some(RuleAction::Ignore));
+        assert_eq!(
+            state.action_for(Some(&app("firefox", "Remote Desktop"))),
+            None
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let state = AppRulesState::default();
+        let mut disabled = rule(Some("vpn"), None, RuleAction::Ignore);
+        disabled.enabled = false;
+        state.set(vec![disabled]);
+        assert_eq!(state.action_for(Some(&app("vpn", ""))), None);
+    }
+
+    #[test]
+    fn validation_rejects_empty_or_invalid_rules() {
+        assert!(rule(None, None, RuleAction::Ignore).validate().is_err());
+        assert!(rule(Some("  "), None, RuleAction::Ignore)
+            .validate()
+            .is_err());
+        assert!(rule(None, Some("("), RuleAction::Ignore)
+            .validate()
+            .is_err());
+        assert!(
+            rule(Some("x"), None, RuleAction::KeepMinutes { minutes: 0 })
+                .validate()
+                .is_err()
+        );
+        assert!(rule(Some("x"), None, RuleAction::TextOnly)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn rules_round_trip_through_storage() {
+        let store = HistoryStore::open_in_memory().unwrap();
+        let saved = store
+            .save_rule(&rule(
+                Some("vpn"),
+                None,
+                RuleAction::KeepMinutes { minutes: 10 },
+            ))
+            .unwrap();
+        let id = saved.id.unwrap();
+        assert_eq!(store.load_rules().unwrap(), vec![saved.clone()]);
+
+        let updated = AppRule {
+            action: RuleAction::TextOnly,
+            ..saved
+        };
+        store.save_rule(&updated).unwrap();
+        assert_eq!(store.load_rules().unwrap()[0].action, RuleAction::TextOnly);
+
+        assert!(store.delete_rule(id).unwrap());
+        assert!(store.load_rules().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::models::Settings;
+use crate::shortcut::ShortcutError;
+use crate::storage::HistoryStore;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::sync::Mutex;
+use tauri_plugin_global_shortcut::Shortcut;
+
+/// 保存设置失败的原因，前端据此给出对应提示
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsError {
+    /// 取值不合法
+    Invalid { message: String },
+    /// 写入数据库失败
+    Storage { message: String },
+    /// 快捷键无法注册，原快捷键保持生效
+    Shortcut { error: ShortcutError },
+}
+
+/// 运行时生效的设置，启动时从数据库加载，保存时整体替换
+pub struct SettingsState {
+    current: Mutex<Settings>,
+}
+
+impl SettingsState {
+    pub fn load(store: &HistoryStore) -> Self {
+        let settings = match store.load_settings() {
+            Ok(rows) => from_rows(rows),
+            Err(e) => {
+                eprintln!("Failed to load settings: {}", e);
+                Settings::default()
+            }
+        };
+        Self {
+            current: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn set(&self, settings: Settings) {
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = settings;
+    }
+}
+
+impl Settings {
+    /// 校验设置取值范围
+    pub fn validate(&self) -> Result<(), String> {
+        if self.shortcut.trim().is_empty() {
+            return Err("快捷键不能为空".to_string());
+        }
+        if !(10..=100_000).contains(&self.max_items) {
+            return Err("最大保存条数需在 10 到 100000 之间".to_string());
+        }
+        if !(1..=3650).contains(&self.max_days) {
+            return Err("保存天数需在 1 到 3650 之间".to_string());
+        }
+        if !(1..=1024).contains(&self.max_file_size_mb) {
+            return Err("单个文件大小上限需在 1 到 1024 MB 之间".to_string());
+        }
+        if !(50..=102_400).contains(&self.max_image_storage_mb) {
+            return Err("图片总空间上限需在 50 到 102400 MB 之间".to_string());
+        }
+        if !(0..=16).contains(&self.similar_image_distance) {
+            return Err("相似图片阈值需在 0 到 16 之间".to_string());
+        }
+        if !(1..=1440).contains(&self.secret_ttl_minutes) {
+            return Err("密钥保留时间需在 1 到 1440 分钟之间".to_string());
+        }
+        for pattern in &self.secret_patterns {
+            if pattern.name.trim().is_empty() {
+                return Err("密钥规则名称不能为空".to_string());
+            }
+            regex::Regex::new(&pattern.pattern)
+                .map_err(|e| format!("密钥规则 {} 无效：{}", pattern.name, e))?;
+        }
+        if !(0..=1000).contains(&self.type_delay_ms) {
+            return Err("逐字输入间隔需在 0 到 1000 毫秒之间".to_string());
+        }
+        let abort_shortcut = self
+            .type_abort_shortcut
+            .parse::<Shortcut>()
+            .map_err(|e| format!("中止输入快捷键无效：{}", e))?;
+        // 与唤出快捷键相同时无法在输入期间注册，也就无法中止
+        if self.shortcut.parse::<Shortcut>().ok() == Some(abort_shortcut) {
+            return Err("中止输入快捷键不能与唤出快捷键相同".to_string());
+        }
+        for rule in &self.paste_chords {
+            if rule.app.trim().is_empty() {
+                return Err("粘贴方式的应用名称不能为空".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// 单条内容允许的最大字节数
+    pub fn max_file_size_bytes(&self) -> i64 {
+        self.max_file_size_mb as i64 * 1024 * 1024
+    }
+
+    /// 图片总共允许占用的字节数
+    pub fn max_image_storage_bytes(&self) -> u64 {
+        self.max_image_storage_mb as u64 * 1024 * 1024
+    }
+}
+
+/// 将 settings 表的键值还原为 Settings，缺失或非法的字段使用默认值
+fn from_rows(rows: Vec<(String, String)>) -> Settings {
+    let map: Map<String, Value> = rows
+        .into_iter()
+        .map(|(key, value)| {
+            // 旧版前端直接写入字符串，无法解析为 JSON 的按字符串处理
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            (key, value)
+        })
+        .collect();
+
+    match serde_json::from_value::<Settings>(Value::Object(map)) {
+        Ok(settings) if settings.validate().is_ok() => settings,
+        Ok(_) | Err(_) => {
+            eprintln!("Stored settings are invalid, falling back to defaults");
+            Settings::default()
+        }
+    }
+}
+
+/// 将 Settings 拆成 settings 表的键值，字符串原样保存，其余保存为 JSON
+pub fn to_rows(settings: &Settings) -> Vec<(String, String)> {
+    let Ok(Value::Object(map)) = serde_json::to_value(settings) else {
+        return Vec::new();
+    };
+    map.into_iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s),
+            other => (key, other.to_string()),
+        })
+        .collect()
+}
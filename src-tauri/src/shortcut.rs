@@ -0,0 +1,111 @@
+use serde::Serialize;
+use std::fmt;
+use std::sync::Mutex;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut};
+
+/// 快捷键注册失败的原因，直接序列化给前端
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ShortcutError {
+    /// 无法解析的快捷键
+    Invalid { accelerator: String, message: String },
+    /// 系统拒绝注册，通常是已被其他程序占用
+    Conflict { accelerator: String, message: String },
+}
+
+impl fmt::Display for ShortcutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Invalid { accelerator, message } => {
+                write!(f, "invalid shortcut {}: {}", accelerator, message)
+            }
+            Self::Conflict { accelerator, message } => {
+                write!(f, "shortcut {} is unavailable: {}", accelerator, message)
+            }
+        }
+    }
+}
+
+/// 当前快捷键注册状态
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShortcutStatus {
+    /// 已注册的快捷键，未注册时为 None
+    pub accelerator: Option<String>,
+    /// 没有任何快捷键生效时，最近一次注册失败的原因
+    pub error: Option<ShortcutError>,
+}
+
+/// 唤起面板的全局快捷键，由后端独占注册
+#[derive(Default)]
+pub struct ShortcutManager {
+    current: Mutex<Option<(String, Shortcut)>>,
+    last_error: Mutex<Option<ShortcutError>>,
+}
+
+impl ShortcutManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 原子替换快捷键：先注册新的，成功后再注销旧的，失败时旧快捷键保持不变
+    pub fn rebind(&self, app: &tauri::AppHandle, accelerator: &str) -> Result<(), ShortcutError> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let shortcut: Shortcut = match accelerator.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                let error = ShortcutError::Invalid {
+                    accelerator: accelerator.to_string(),
+                    message: format!("{}", e),
+                };
+                return Err(self.fail(current.is_none(), error));
+            }
+        };
+
+        if let Some((_, existing)) = current.as_ref() {
+            if *existing == shortcut {
+                *current = Some((accelerator.to_string(), shortcut));
+                return Ok(());
+            }
+        }
+
+        let global_shortcut = app.global_shortcut();
+        if let Err(e) = global_shortcut.register(shortcut) {
+            let error = ShortcutError::Conflict {
+                accelerator: accelerator.to_string(),
+                message: e.to_string(),
+            };
+            return Err(self.fail(current.is_none(), error));
+        }
+
+        if let Some((_, old)) = current.take() {
+            let _ = global_shortcut.unregister(old);
+        }
+        *current = Some((accelerator.to_string(), shortcut));
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        Ok(())
+    }
+
+    /// 没有任何快捷键生效时记下失败原因，供前端查询
+    fn fail(&self, unbound: bool, error: ShortcutError) -> ShortcutError {
+        if unbound {
+            *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.clone());
+        }
+        error
+    }
+
+    pub fn status(&self) -> ShortcutStatus {
+        ShortcutStatus {
+            accelerator: self
+                .current
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .as_ref()
+                .map(|(accelerator, _)| accelerator.clone()),
+            error: self
+                .last_error
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+        }
+    }
+}
@@ -0,0 +1,857 @@
+use crate::clipboard;
+use crate::crypto::{self, Cipher};
+use crate::models::{
+    AppRule, ClipboardFormat, ClipboardItem, ClipboardPayload, FileEntry, RuleAction, SourceApp,
+};
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// 数据库文件名（与旧版前端 tauri-plugin-sql 使用的文件保持一致）
+pub const DB_FILE: &str = "super-paste.db";
+
+/// 列表/搜索默认返回的最大条数
+pub const DEFAULT_LIST_LIMIT: u32 = 100;
+
+/// 按顺序执行的数据库迁移，`PRAGMA user_version` 记录已执行的数量
+const MIGRATIONS: &[&str] = &[r#"
+    CREATE TABLE IF NOT EXISTS clipboard_items (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        content_type TEXT NOT NULL,
+        content_hash TEXT NOT NULL UNIQUE,
+        text_content TEXT,
+        image_path TEXT,
+        thumbnail_path TEXT,
+        file_size INTEGER DEFAULT 0,
+        source_app TEXT,
+        created_at TEXT DEFAULT (datetime('now')),
+        updated_at TEXT DEFAULT (datetime('now'))
+    );
+    CREATE INDEX IF NOT EXISTS idx_updated_at ON clipboard_items(updated_at DESC);
+"#, r#"
+    CREATE TABLE IF NOT EXISTS settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+"#, r#"
+    CREATE TABLE IF NOT EXISTS clipboard_formats (
+        item_id INTEGER NOT NULL REFERENCES clipboard_items(id) ON DELETE CASCADE,
+        mime_type TEXT NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (item_id, mime_type)
+    );
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN files TEXT;
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN expires_at TEXT;
+    CREATE TABLE IF NOT EXISTS app_rules (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        app_id TEXT,
+        window_class TEXT,
+        title_pattern TEXT,
+        action TEXT NOT NULL,
+        enabled INTEGER NOT NULL DEFAULT 1
+    );
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN sensitive TEXT;
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN missing INTEGER NOT NULL DEFAULT 0;
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN perceptual_hash TEXT;
+"#, r#"
+    ALTER TABLE clipboard_items ADD COLUMN plain_hash TEXT;
+"#];
+
+const ITEM_COLUMNS: &str = "id, content_type, content_hash, text_content, image_path, \
+     thumbnail_path, file_size, source_app, created_at, updated_at, files, expires_at, sensitive, \
+     pinned, missing, (SELECT group_concat(mime_type, char(10)) FROM clipboard_formats WHERE item_id = clipboard_items.id), \
+     plain_hash";
+
+/// 图片条目及其原图、缩略图文件
+pub struct ImageEntry {
+    pub id: i64,
+    pub pinned: bool,
+    pub paths: Vec<String>,
+}
+
+/// 条目引用的原图和缩略图文件
+pub struct FileRefs {
+    pub id: i64,
+    pub content_type: String,
+    pub content_hash: String,
+    pub image_path: Option<String>,
+    pub thumbnail_path: Option<String>,
+    pub missing: bool,
+}
+
+/// 剪贴板历史存储，独占 clipboard_items 表
+pub struct HistoryStore {
+    conn: Mutex<Connection>,
+    /// 开启静态加密并解锁后的密钥，文本列、额外格式和图片文件用它加解密
+    cipher: RwLock<Option<Arc<Cipher>>>,
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> rusqlite::Result<Self> {
+        // 删除条目时级联删除其额外格式
+        conn.pragma_update(None, "foreign_keys", true)?;
+        // 删除和改写时用零覆盖旧内容，不在空闲页中留下明文
+        conn.pragma_update(None, "secure_delete", true)?;
+        migrate(&conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            cipher: RwLock::new(None),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // 持锁线程 panic 不会破坏 SQLite 连接本身，继续使用即可
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set_cipher(&self, cipher: Option<Arc<Cipher>>) {
+        *self.cipher.write().unwrap_or_else(|e| e.into_inner()) = cipher;
+    }
+
+    pub fn has_cipher(&self) -> bool {
+        self.cipher().is_some()
+    }
+
+    pub fn cipher(&self) -> Option<Arc<Cipher>> {
+        self.cipher
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// 读取图片文件，加密过的文件自动解密
+    pub fn read_image(&self, path: &Path) -> Result<Vec<u8>, String> {
+        crypto::read_file(path, self.cipher().as_deref())
+    }
+
+    /// 开启加密时就地加密新写入的图片文件
+    pub fn protect_file(&self, path: &Path) -> Result<(), String> {
+        let cipher = self.cipher();
+        // 持有连接锁，避免与入库时的加密同时改写同一文件
+        let _conn = self.conn();
+        protect_file(cipher.as_deref(), path)
+    }
+
+    /// 写入一条捕获：已存在相同 hash 时只刷新时间，否则插入新记录。
+    /// 额外格式和过期时间总是替换为本次捕获的内容
+    pub fn upsert(&self, payload: &ClipboardPayload) -> rusqlite::Result<ClipboardItem> {
+        let cipher = self.cipher();
+        let cipher = cipher.as_deref();
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        // 开启加密时按带密钥的 hash 去重，原始 hash 加密保存在 plain_hash
+        let key = crypto::content_key(cipher, &payload.content_hash);
+        // 来源应用含窗口标题，和文本一样加密
+        let source_app = encrypt_text(cipher, source_app_to_json(payload.source_app.as_ref()));
+        // datetime('now', NULL) 为 NULL，不限时的条目不会过期
+        let expiry = payload.keep_minutes.map(|m| format!("+{m} minutes"));
+        let updated = tx.execute(
+            "UPDATE clipboard_items SET updated_at = datetime('now'), source_app = COALESCE(?2, source_app),
+                 expires_at = datetime('now', ?3), sensitive = ?4
+             WHERE content_hash = ?1",
+            params![key, source_app, expiry, payload.sensitive],
+        )?;
+
+        if updated == 0 {
+            for path in [&payload.image_path, &payload.thumbnail_path]
+                .into_iter()
+                .flatten()
+            {
+                if let Err(e) = protect_file(cipher, Path::new(path)) {
+                    eprintln!("Failed to encrypt image file: {}", e);
+                }
+            }
+            tx.execute(
+                "INSERT INTO clipboard_items (content_type, content_hash, text_content, image_path, thumbnail_path, file_size, files, source_app, expires_at, sensitive, perceptual_hash, plain_hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now', ?9), ?10, ?11, ?12)",
+                params![
+                    payload.content_type,
+                    key,
+                    encrypt_text(cipher, payload.text_content.clone()),
+                    payload.image_path,
+                    payload.thumbnail_path,
+                    payload.file_size,
+                    encrypt_text(cipher, files_to_json(&payload.files)),
+                    source_app,
+                    expiry,
+                    payload.sensitive,
+                    payload.perceptual_hash,
+                    cipher.map(|cipher| cipher.encrypt_text(&payload.content_hash)),
+                ],
+            )?;
+        }
+
+        let id: i64 = tx.query_row(
+            "SELECT id FROM clipboard_items WHERE content_hash = ?1",
+            params![key],
+            |row| row.get(0),
+        )?;
+        tx.execute("DELETE FROM clipboard_formats WHERE item_id = ?1", params![id])?;
+        for format in &payload.formats {
+            tx.execute(
+                "INSERT OR REPLACE INTO clipboard_formats (item_id, mime_type, data) VALUES (?1, ?2, ?3)",
+                params![id, format.mime_type, encrypt_blob(cipher, &format.data)],
+            )?;
+        }
+
+        let item = get_item(&tx, id, cipher)?.ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        tx.commit()?;
+        Ok(item)
+    }
+
+    /// 读取条目保存的全部额外格式
+    pub fn formats(&self, id: i64) -> rusqlite::Result<Vec<ClipboardFormat>> {
+        let cipher = self.cipher();
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT mime_type, data FROM clipboard_formats WHERE item_id = ?1 ORDER BY mime_type",
+        )?;
+        let formats = stmt
+            .query_map(params![id], |row| {
+                Ok(ClipboardFormat {
+                    mime_type: row.get(0)?,
+                    data: crypto::decrypt_blob(cipher.as_deref(), row.get(1)?).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(1, Type::Blob, e.into())
+                    })?,
+                })
+            })?
+            .collect();
+        formats
+    }
+
+    /// 删除已过期的条目，以及超出条数或天数限制的未固定条目，同时删除它们的图片文件。
+    /// 返回删除的条数
+    pub fn apply_retention(&self, max_items: u32, max_days: u32) -> rusqlite::Result<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let doomed: Vec<(i64, Option<String>, Option<String>)> = tx
+            .prepare(
+                "SELECT id, image_path, thumbnail_path FROM clipboard_items
+                 WHERE expires_at <= datetime('now')
+                    OR (pinned = 0 AND (updated_at < datetime('now', ?2) OR id NOT IN (
+                        SELECT id FROM clipboard_items WHERE pinned = 0
+                        ORDER BY updated_at DESC, id DESC LIMIT ?1
+                    )))",
+            )?
+            .query_map(params![max_items, format!("-{max_days} days")], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        for (id, _, _) in &doomed {
+            tx.execute("DELETE FROM clipboard_items WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        drop(conn);
+
+        // 记录删除成功后再删文件，失败回滚时文件仍然可用
+        for (_, image_path, thumbnail_path) in &doomed {
+            self.delete_files(image_path.as_deref(), thumbnail_path.as_deref());
+        }
+        Ok(doomed.len())
+    }
+
+    /// 按时间倒序列出条目，指定 app 时只返回该应用复制的内容。密钥条目的文本被遮盖。
+    /// 开启加密后来源应用是密文，改为解密后在内存中过滤
+    pub fn list(&self, limit: u32, app: Option<&str>) -> rusqlite::Result<Vec<ClipboardItem>> {
+        let cipher = self.cipher();
+        let sql_app = app.filter(|_| cipher.is_none());
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ITEM_COLUMNS} FROM clipboard_items
+             WHERE (?1 IS NULL OR json_extract(source_app, '$.id') = ?1)
+               AND (expires_at IS NULL OR expires_at > datetime('now'))
+             ORDER BY updated_at DESC"
+        ))?;
+        let mut items = Vec::new();
+        for item in stmt.query_map(params![sql_app], |row| row_to_item(row, cipher.as_deref()))? {
+            let item = item?;
+            if from_app(&item, app) {
+                items.push(mask_sensitive(item));
+                if items.len() >= limit as usize {
+                    break;
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// 按文本搜索，密钥条目不参与匹配，避免通过搜索逐字试出内容。
+    /// 开启加密后 SQL 无法匹配密文，改为解密后在内存中匹配
+    pub fn search(
+        &self,
+        query: &str,
+        limit: u32,
+        app: Option<&str>,
+    ) -> rusqlite::Result<Vec<ClipboardItem>> {
+        let cipher = self.cipher();
+        let pattern = match cipher {
+            Some(_) => None,
+            None => Some(format!("%{}%", escape_like(query))),
+        };
+        let sql_app = app.filter(|_| cipher.is_none());
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {ITEM_COLUMNS} FROM clipboard_items
+             WHERE (?1 IS NULL OR text_content LIKE ?1 ESCAPE '\\') AND sensitive IS NULL
+               AND (?2 IS NULL OR json_extract(source_app, '$.id') = ?2)
+               AND (expires_at IS NULL OR expires_at > datetime('now'))
+             ORDER BY updated_at DESC"
+        ))?;
+        let needle = query.to_lowercase();
+        let mut items = Vec::new();
+        for item in stmt.query_map(params![pattern, sql_app], |row| {
+            row_to_item(row, cipher.as_deref())
+        })? {
+            let item = item?;
+            let matched = (pattern.is_some()
+                || item
+                    .text_content
+                    .as_deref()
+                    .is_some_and(|text| text.to_lowercase().contains(&needle)))
+                && from_app(&item, app);
+            if matched {
+                items.push(item);
+                if items.len() >= limit as usize {
+                    break;
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    pub fn get(&self, id: i64) -> rusqlite::Result<Option<ClipboardItem>> {
+        let cipher = self.cipher();
+        get_item(&self.conn(), id, cipher.as_deref())
+    }
+
+    /// 删除记录并返回被删除的条目，方便调用方清理图片文件
+    pub fn delete(&self, id: i64) -> rusqlite::Result<Option<ClipboardItem>> {
+        let cipher = self.cipher();
+        let conn = self.conn();
+        let item = get_item(&conn, id, cipher.as_deref())?;
+        if item.is_some() {
+            conn.execute("DELETE FROM clipboard_items WHERE id = ?1", params![id])?;
+        }
+        Ok(item)
+    }
+
+    /// 查找与 dHash 相差不超过 max_distance 位、原图仍在的图片条目，取最接近的一条。
+    /// 已有相同内容 hash 的条目时返回 None，由 upsert 直接刷新
+    pub fn similar_image(
+        &self,
+        hash: &str,
+        perceptual_hash: &str,
+        max_distance: u32,
+    ) -> rusqlite::Result<Option<ClipboardItem>> {
+        let cipher = self.cipher();
+        let conn = self.conn();
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM clipboard_items WHERE content_hash = ?1)",
+            params![crypto::content_key(cipher.as_deref(), hash)],
+            |row| row.get(0),
+        )?;
+        if exists {
+            return Ok(None);
+        }
+        let mut stmt = conn.prepare(
+            "SELECT id, perceptual_hash FROM clipboard_items
+             WHERE content_type = 'image' AND perceptual_hash IS NOT NULL AND missing = 0
+             ORDER BY updated_at DESC, id DESC",
+        )?;
+        let candidates = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let closest = candidates
+            .into_iter()
+            .filter_map(|(id, other)| {
+                let distance = clipboard::perceptual_distance(perceptual_hash, &other)?;
+                (distance <= max_distance).then_some((distance, id))
+            })
+            .min_by_key(|(distance, _)| *distance);
+        match closest {
+            Some((_, id)) => get_item(&conn, id, cipher.as_deref()),
+            None => Ok(None),
+        }
+    }
+
+    /// 数据库和图片文件名中使用的 hash，开启加密时为带密钥的 hash
+    pub fn content_key(&self, hash: &str) -> String {
+        crypto::content_key(self.cipher().as_deref(), hash)
+    }
+
+    /// 是否已有相同内容 hash 的条目，入库前用来区分新插入和刷新
+    pub fn contains_hash(&self, hash: &str) -> rusqlite::Result<bool> {
+        self.conn().query_row(
+            "SELECT EXISTS(SELECT 1 FROM clipboard_items WHERE content_hash = ?1)",
+            params![self.content_key(hash)],
+            |row| row.get(0),
+        )
+    }
+
+    /// 读取 settings 表中的全部键值
+    pub fn load_settings(&self) -> rusqlite::Result<Vec<(String, String)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key, value FROM settings")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        rows
+    }
+
+    /// 在一个事务内写入全部设置，要么全部生效要么全部不变
+    pub fn save_settings(&self, rows: &[(String, String)]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for (key, value) in rows {
+            tx.execute(
+                "INSERT OR REPLACE INTO settings (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// 按创建顺序读取全部应用规则
+    pub fn load_rules(&self) -> rusqlite::Result<Vec<AppRule>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, app_id, window_class, title_pattern, action, enabled FROM app_rules ORDER BY id",
+        )?;
+        let rules = stmt.query_map([], row_to_rule)?.collect();
+        rules
+    }
+
+    /// 保存规则：没有 id 时新建，否则覆盖同 id 的规则
+    pub fn save_rule(&self, rule: &AppRule) -> rusqlite::Result<AppRule> {
+        let conn = self.conn();
+        let action = serde_json::to_string(&rule.action)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(e.into()))?;
+        let id = match rule.id {
+            Some(id) => {
+                let updated = conn.execute(
+                    "UPDATE app_rules SET app_id = ?2, window_class = ?3, title_pattern = ?4, action = ?5, enabled = ?6
+                     WHERE id = ?1",
+                    params![id, rule.app_id, rule.window_class, rule.title_pattern, action, rule.enabled],
+                )?;
+                if updated == 0 {
+                    return Err(rusqlite::Error::QueryReturnedNoRows);
+                }
+                id
+            }
+            None => {
+                conn.execute(
+                    "INSERT INTO app_rules (app_id, window_class, title_pattern, action, enabled)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![rule.app_id, rule.window_class, rule.title_pattern, action, rule.enabled],
+                )?;
+                conn.last_insert_rowid()
+            }
+        };
+        Ok(AppRule {
+            id: Some(id),
+            ..rule.clone()
+        })
+    }
+
+    pub fn delete_rule(&self, id: i64) -> rusqlite::Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM app_rules WHERE id = ?1", params![id])?;
+        Ok(deleted > 0)
+    }
+
+    /// 用新密钥重新加密全部文本列、额外格式和图片文件，new 为 None 时解密回明文。
+    /// 内容 hash 和以它命名的图片文件同时换成新密钥下的 hash。
+    /// 数据按前缀区分明文和密文，中途失败后重试不会重复加密。任何一项无法解密或
+    /// 改写时整体失败并回滚数据库，调用方不能因此丢弃旧密钥
+    pub fn reencrypt(&self, new: Option<Arc<Cipher>>) -> Result<(), String> {
+        struct Stored {
+            id: i64,
+            key: String,
+            plain_hash: Option<String>,
+            /// 文本、文件列表和来源应用三列
+            texts: [Option<String>; 3],
+            /// 原图和缩略图
+            paths: [Option<String>; 2],
+        }
+
+        let old = self.cipher();
+        let (old, new_ref) = (old.as_deref(), new.as_deref());
+        let mut conn = self.conn();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        let rows: Vec<Stored> = tx
+            .prepare(
+                "SELECT id, content_hash, plain_hash, text_content, files, source_app, image_path, thumbnail_path
+                 FROM clipboard_items",
+            )
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| {
+                    Ok(Stored {
+                        id: row.get(0)?,
+                        key: row.get(1)?,
+                        plain_hash: row.get(2)?,
+                        texts: [row.get(3)?, row.get(4)?, row.get(5)?],
+                        paths: [row.get(6)?, row.get(7)?],
+                    })
+                })?
+                .collect()
+            })
+            .map_err(|e| e.to_string())?;
+        let decrypt = |id: i64, value: Option<String>| match value {
+            Some(value) => crypto::decrypt_text(old, value)
+                .map(Some)
+                .ok_or_else(|| format!("Failed to decrypt history item {}", id)),
+            None => Ok(None),
+        };
+        // 需要改写的文件及改写后的路径，多个条目共用的文件只改写一次
+        let mut moves: Vec<(String, String)> = Vec::new();
+        for row in rows {
+            let id = row.id;
+            let [text, files, source_app] = row.texts.map(|value| decrypt(id, value));
+            let plain_hash = decrypt(id, row.plain_hash)?.unwrap_or_else(|| row.key.clone());
+            let key = crypto::content_key(new_ref, &plain_hash);
+            let [image_path, thumbnail_path] = row.paths.map(|path| {
+                let path = path?;
+                let dest = rename_for_key(&path, &row.key, &key);
+                if !moves.iter().any(|(src, _)| *src == path) {
+                    moves.push((path, dest.clone()));
+                }
+                Some(dest)
+            });
+            tx.execute(
+                "UPDATE clipboard_items SET text_content = ?2, files = ?3, source_app = ?4,
+                     content_hash = ?5, plain_hash = ?6, image_path = ?7, thumbnail_path = ?8
+                 WHERE id = ?1",
+                params![
+                    id,
+                    encrypt_text(new_ref, text?),
+                    encrypt_text(new_ref, files?),
+                    encrypt_text(new_ref, source_app?),
+                    key,
+                    new_ref.map(|cipher| cipher.encrypt_text(&plain_hash)),
+                    image_path,
+                    thumbnail_path
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        let formats: Vec<(i64, String, Vec<u8>)> = tx
+            .prepare("SELECT item_id, mime_type, data FROM clipboard_formats")
+            .and_then(|mut stmt| {
+                stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect()
+            })
+            .map_err(|e| e.to_string())?;
+        for (id, mime_type, data) in formats {
+            let data = crypto::decrypt_blob(old, data)
+                .map_err(|e| format!("Failed to decrypt {} of item {}: {}", mime_type, id, e))?;
+            tx.execute(
+                "UPDATE clipboard_formats SET data = ?3 WHERE item_id = ?1 AND mime_type = ?2",
+                params![id, mime_type, encrypt_blob(new_ref, &data)],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        // 文件改写无法回滚，放在数据库更新之后。改名的文件先写到新路径，
+        // 失败回滚后旧文件仍在，新文件由维护任务作为孤立文件清理
+        for (src, dest) in &moves {
+            // 已丢失的文件没有需要保留的内容
+            if !Path::new(src).exists() {
+                continue;
+            }
+            crypto::rewrite_file(Path::new(src), Path::new(dest), old, new_ref)
+                .map_err(|e| format!("Failed to re-encrypt {}: {}", src, e))?;
+        }
+
+        tx.commit().map_err(|e| e.to_string())?;
+        drop(conn);
+        self.set_cipher(new);
+        for (src, dest) in &moves {
+            if src != dest {
+                let _ = std::fs::remove_file(src);
+            }
+        }
+        // 改写前的内容可能还在开启 secure_delete 之前的空闲页和 WAL 中，
+        // 整理数据库并截断 WAL 后才真正清除
+        self.conn()
+            .execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| e.to_string())
+    }
+
+    /// 固定或取消固定条目
+    pub fn set_pinned(&self, id: i64, pinned: bool) -> rusqlite::Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE clipboard_items SET pinned = ?2 WHERE id = ?1",
+            params![id, pinned],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// 全部图片条目，按更新时间从旧到新排列，用于统计和清理图片占用的空间
+    pub fn image_entries(&self) -> rusqlite::Result<Vec<ImageEntry>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, pinned, image_path, thumbnail_path FROM clipboard_items
+             WHERE content_type = 'image' ORDER BY updated_at ASC, id ASC",
+        )?;
+        let entries = stmt
+            .query_map([], |row| {
+                Ok(ImageEntry {
+                    id: row.get(0)?,
+                    pinned: row.get(1)?,
+                    paths: [row.get::<_, Option<String>>(2)?, row.get(3)?]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                })
+            })?
+            .collect();
+        entries
+    }
+
+    /// 删除已删除条目的原图和缩略图。文件按内容命名可能被多个条目共用，仍被引用的保留
+    pub fn delete_files(&self, image_path: Option<&str>, thumbnail_path: Option<&str>) {
+        let conn = self.conn();
+        for path in [image_path, thumbnail_path].into_iter().flatten() {
+            if path.is_empty() {
+                continue;
+            }
+            let in_use = conn
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM clipboard_items WHERE image_path = ?1 OR thumbnail_path = ?1)",
+                    params![path],
+                    |row| row.get(0),
+                )
+                .unwrap_or(true);
+            if !in_use {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// 全部引用了图片文件的条目，用于清理孤立文件和修复记录
+    pub fn file_refs(&self) -> rusqlite::Result<Vec<FileRefs>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, content_type, content_hash, image_path, thumbnail_path, missing FROM clipboard_items
+             WHERE image_path IS NOT NULL OR thumbnail_path IS NOT NULL",
+        )?;
+        let refs = stmt
+            .query_map([], |row| {
+                Ok(FileRefs {
+                    id: row.get(0)?,
+                    content_type: row.get(1)?,
+                    content_hash: row.get(2)?,
+                    image_path: row.get(3)?,
+                    thumbnail_path: row.get(4)?,
+                    missing: row.get(5)?,
+                })
+            })?
+            .collect();
+        refs
+    }
+
+    pub fn set_thumbnail(&self, id: i64, path: Option<&str>) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE clipboard_items SET thumbnail_path = ?2 WHERE id = ?1",
+            params![id, path],
+        )?;
+        Ok(())
+    }
+
+    /// 标记原图是否已丢失
+    pub fn set_missing(&self, id: i64, missing: bool) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE clipboard_items SET missing = ?2 WHERE id = ?1",
+            params![id, missing],
+        )?;
+        Ok(())
+    }
+
+    /// 刷新条目时间，使其排到最前
+    pub fn touch(&self, id: i64) -> rusqlite::Result<bool> {
+        let updated = self.conn().execute(
+            "UPDATE clipboard_items SET updated_at = datetime('now') WHERE id = ?1",
+            params![id],
+        )?;
+        Ok(updated > 0)
+    }
+}
+
+fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        conn.execute_batch(sql)?;
+        conn.pragma_update(None, "user_version", i + 1)?;
+    }
+    Ok(())
+}
+
+fn get_item(
+    conn: &Connection,
+    id: i64,
+    cipher: Option<&Cipher>,
+) -> rusqlite::Result<Option<ClipboardItem>> {
+    conn.query_row(
+        &format!("SELECT {ITEM_COLUMNS} FROM clipboard_items WHERE id = ?1"),
+        params![id],
+        |row| row_to_item(row, cipher),
+    )
+    .optional()
+}
+
+/// 读取一行条目，加密的文本列在未解锁时为空。内容 hash 优先取解密后的原始 hash
+fn row_to_item(row: &Row, cipher: Option<&Cipher>) -> rusqlite::Result<ClipboardItem> {
+    let plain_hash = row
+        .get::<_, Option<String>>(16)?
+        .and_then(|hash| crypto::decrypt_text(cipher, hash));
+    Ok(ClipboardItem {
+        id: row.get(0)?,
+        content_type: row.get(1)?,
+        content_hash: match plain_hash {
+            Some(hash) => hash,
+            None => row.get(2)?,
+        },
+        text_content: row
+            .get::<_, Option<String>>(3)?
+            .and_then(|text| crypto::decrypt_text(cipher, text)),
+        image_path: row.get(4)?,
+        thumbnail_path: row.get(5)?,
+        file_size: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+        source_app: row
+            .get::<_, Option<String>>(7)?
+            .and_then(|json| crypto::decrypt_text(cipher, json))
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        files: row
+            .get::<_, Option<String>>(10)?
+            .and_then(|json| crypto::decrypt_text(cipher, json))
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        expires_at: row.get(11)?,
+        sensitive: row.get(12)?,
+        pinned: row.get(13)?,
+        missing: row.get(14)?,
+        pending: false,
+        formats: row
+            .get::<_, Option<String>>(15)?
+            .map(|types| types.lines().map(str::to_string).collect())
+            .unwrap_or_default(),
+    })
+}
+
+/// 密钥条目只保留开头几个字符，其余替换为圆点
+pub fn mask_sensitive(mut item: ClipboardItem) -> ClipboardItem {
+    if item.sensitive.is_some() {
+        if let Some(text) = item.text_content.as_mut() {
+            let visible: String = text.chars().take(4).collect();
+            let hidden = text.chars().count().saturating_sub(4).clamp(4, 16);
+            *text = format!("{}{}", visible, "•".repeat(hidden));
+        }
+    }
+    item
+}
+
+fn row_to_rule(row: &Row) -> rusqlite::Result<AppRule> {
+    let action: String = row.get(4)?;
+    Ok(AppRule {
+        id: row.get(0)?,
+        app_id: row.get(1)?,
+        window_class: row.get(2)?,
+        title_pattern: row.get(3)?,
+        // 无法识别的动作按忽略处理，宁可少记也不误记
+        action: serde_json::from_str(&action).unwrap_or(RuleAction::Ignore),
+        enabled: row.get(5)?,
+    })
+}
+
+/// 文件列表以 JSON 保存，非 files 类型条目为 NULL
+fn files_to_json(files: &[FileEntry]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+    serde_json::to_string(files).ok()
+}
+
+fn encrypt_text(cipher: Option<&Cipher>, text: Option<String>) -> Option<String> {
+    match cipher {
+        Some(cipher) => text.map(|t| cipher.encrypt_text(&t)),
+        None => text,
+    }
+}
+
+fn protect_file(cipher: Option<&Cipher>, path: &Path) -> Result<(), String> {
+    match cipher {
+        // 后台尚未写完的图片由写入线程自行加密
+        Some(cipher) if path.exists() => crypto::rewrite_file(path, path, None, Some(cipher)),
+        _ => Ok(()),
+    }
+}
+
+/// 以旧 hash 开头命名的图片文件改用新 hash 命名，其他文件保持原路径
+fn rename_for_key(path: &str, old_key: &str, new_key: &str) -> String {
+    let path = Path::new(path);
+    match path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| name.strip_prefix(old_key))
+    {
+        Some(rest) => path
+            .with_file_name(format!("{new_key}{rest}"))
+            .to_string_lossy()
+            .to_string(),
+        None => path.to_string_lossy().to_string(),
+    }
+}
+
+fn encrypt_blob(cipher: Option<&Cipher>, data: &[u8]) -> Vec<u8> {
+    match cipher {
+        Some(cipher) => cipher.encrypt_blob(data),
+        None => data.to_vec(),
+    }
+}
+
+/// 未指定应用或条目来自该应用
+fn from_app(item: &ClipboardItem, app: Option<&str>) -> bool {
+    app.is_none_or(|app| {
+        item.source_app
+            .as_ref()
+            .is_some_and(|source| source.id.as_deref() == Some(app))
+    })
+}
+
+fn source_app_to_json(app: Option<&SourceApp>) -> Option<String> {
+    serde_json::to_string(app?).ok()
+}
+
+fn escape_like(query: &str) -> String {
+    query
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
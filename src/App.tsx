@@ -7,19 +7,25 @@ import { useSettings } from './hooks/useSettings';
 import ClipboardCard from './components/ClipboardCard';
 import SearchBar, { SearchBarRef } from './components/SearchBar';
 import SettingsPanel from './components/SettingsPanel';
-import type { ClipboardItem } from './types';
+import type { ClipboardItem, EncryptionStatus, ImageEviction, ImageReady, SkippedCapture } from './types';
 
 function App() {
-  const { items, updateItemTime, searchItems, deleteItem } = useClipboard();
-  const { settings, updateShortcut, updateSettings } = useSettings();
+  const { items, appFilter, searchItems, filterByApp, deleteItem, togglePin } = useClipboard();
+  const { settings, shortcutStatus, updateShortcut, updateSettings } = useSettings();
   
   // 核心状态：-1 表示搜索模式，>=0 表示选中的卡片索引
   const [selectedIndex, setSelectedIndex] = useState(-1);
   const [searchQuery, setSearchQuery] = useState('');
   const [showSettings, setShowSettings] = useState(false);
+  const [notice, setNotice] = useState<string | null>(null);
+  const [locked, setLocked] = useState(false);
+  const [passphrase, setPassphrase] = useState('');
+  const [unlockError, setUnlockError] = useState('');
   
   const searchBarRef = useRef<SearchBarRef>(null);
   const cardRefs = useRef<(HTMLDivElement | null)[]>([]);
+  // 粘贴时面板已隐藏，失败原因留到下次打开面板时提示
+  const pasteErrorRef = useRef<string | null>(null);
 
   // items 变化时，清理多余的 refs
   useEffect(() => {
@@ -94,7 +100,12 @@ function App() {
         case 'Enter':
           if (selectedIndex >= 0 && items[selectedIndex]) {
             e.preventDefault();
-            await handleCopyAndPaste(items[selectedIndex]);
+            // Shift+Enter 把文本逐字输入，用于不接受粘贴的目标
+            if (e.shiftKey) {
+              await handleTypeOut(items[selectedIndex]);
+            } else {
+              await handleCopyAndPaste(items[selectedIndex]);
+            }
           }
           break;
 
@@ -119,17 +130,68 @@ function App() {
     return () => window.removeEventListener('keydown', handleKeyDown);
   }, [items, selectedIndex, enterCardMode, enterSearchMode]);
 
+  // 口令加密的历史需要先解锁
+  const checkLocked = useCallback(async () => {
+    try {
+      const status = await invoke<EncryptionStatus>('get_encryption_status');
+      setLocked(status.locked);
+    } catch (e) {
+      console.error('Failed to get encryption status:', e);
+    }
+  }, []);
+
+  useEffect(() => {
+    checkLocked();
+  }, [checkLocked]);
+
   // 面板显示时重置状态
   useEffect(() => {
     const unlisten = listen('panel-show', () => {
+      checkLocked();
       setSearchQuery('');
       setSelectedIndex(-1);
+      if (pasteErrorRef.current) {
+        setNotice(pasteErrorRef.current);
+        pasteErrorRef.current = null;
+        setTimeout(() => setNotice(null), 5000);
+      }
       // 等待窗口渲染完成后聚焦
       requestAnimationFrame(() => {
         searchBarRef.current?.focus();
       });
     });
     return () => { unlisten.then(fn => fn()); };
+  }, [checkLocked]);
+
+  // 敏感内容或命中规则的内容被跳过时短暂提示
+  useEffect(() => {
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const showNotice = (text: string) => {
+      setNotice(text);
+      clearTimeout(timer);
+      timer = setTimeout(() => setNotice(null), 3000);
+    };
+    const unlisten = listen<SkippedCapture>('clipboard-skipped', (event) => {
+      const app = event.payload.source_app;
+      const from = app?.name ?? app?.id;
+      const what = event.payload.reason === 'rule' ? '内容（应用规则）' : '敏感内容';
+      showNotice(from ? `已忽略来自 ${from} 的${what}` : `已忽略一条${what}`);
+    });
+    // 图片空间超限时后端清理了最旧的图片
+    const unlistenEviction = listen<ImageEviction>('images-evicted', (event) => {
+      const { evicted, freed_bytes } = event.payload;
+      const freedMb = (freed_bytes / 1024 / 1024).toFixed(1);
+      showNotice(`图片空间已满，清理了 ${evicted.length} 张旧图片（${freedMb} MB）`);
+    });
+    const unlistenImage = listen<ImageReady>('image-ready', (event) => {
+      if (event.payload.error) showNotice(`图片保存失败：${event.payload.error}`);
+    });
+    return () => {
+      clearTimeout(timer);
+      unlisten.then(fn => fn());
+      unlistenEviction.then(fn => fn());
+      unlistenImage.then(fn => fn());
+    };
   }, []);
 
   // 窗口失焦时隐藏
@@ -144,35 +206,35 @@ function App() {
   }, []);
 
   const handleCopy = async (item: ClipboardItem) => {
-    const content = item.content_type === 'text'
-      ? item.text_content!
-      : item.image_path!;
-
-    await invoke('copy_to_clipboard', {
-      content,
-      contentType: item.content_type
-    });
-    await updateItemTime(item.content_hash);
+    // 后端按 id 读取条目，连同 HTML/RTF 等格式一起写回，并按设置决定是否移到最前
+    await invoke('copy_to_clipboard', { id: item.id });
     await invoke('hide_window');
   };
 
   // 复制并粘贴（用于回车键和鼠标点击）
   const handleCopyAndPaste = async (item: ClipboardItem) => {
-    const content = item.content_type === 'text'
-      ? item.text_content!
-      : item.image_path!;
-
-    await invoke('copy_to_clipboard', {
-      content,
-      contentType: item.content_type
-    });
-    await updateItemTime(item.content_hash);
     await invoke('hide_window');
-    // 执行粘贴（延迟在 Rust 端处理）
+    // 后端按 id 读取条目，连同 HTML/RTF 等格式一起写回后粘贴，延迟和粘贴后恢复剪贴板都在 Rust 端处理
     try {
-      await invoke('paste');
+      await invoke('paste', { id: item.id });
     } catch (e) {
       console.error('Paste failed:', e);
+      pasteErrorRef.current = `粘贴失败：${e}`;
+    }
+  };
+
+  // 逐字输入文本条目，不经过剪贴板
+  const handleTypeOut = async (item: ClipboardItem) => {
+    if (item.content_type !== 'text') {
+      setNotice('只有文本条目可以逐字输入');
+      return;
+    }
+    await invoke('hide_window');
+    try {
+      await invoke('paste', { id: item.id, typeOut: true });
+    } catch (e) {
+      console.error('Type out failed:', e);
+      pasteErrorRef.current = `逐字输入失败：${e}`;
     }
   };
 
@@ -193,6 +255,18 @@ function App() {
     }
   };
 
+  const handleUnlock = async () => {
+    setUnlockError('');
+    try {
+      await invoke('unlock_history', { passphrase });
+      setPassphrase('');
+      setLocked(false);
+      requestAnimationFrame(() => searchBarRef.current?.focus());
+    } catch (e) {
+      setUnlockError(String(e));
+    }
+  };
+
   const handleCardClick = (index: number) => {
     setSelectedIndex(index);
     handleCopyAndPaste(items[index]);
@@ -205,6 +279,8 @@ function App() {
           ref={searchBarRef}
           value={searchQuery} 
           onChange={handleSearch}
+          appFilter={appFilter}
+          onClearAppFilter={() => filterByApp(null)}
         />
         
         <div className="cards-container">
@@ -222,6 +298,8 @@ function App() {
                   item={item}
                   isSelected={index === selectedIndex}
                   onClick={() => handleCardClick(index)}
+                  onFilterApp={filterByApp}
+                  onTogglePin={() => togglePin(item)}
                 />
               ))}
             </div>
@@ -229,18 +307,51 @@ function App() {
         </div>
 
         <div className="footer">
+          {notice && <span className="notice">{notice}</span>}
           <span className="hint">↓ 选择卡片</span>
           <span className="hint">← → 切换</span>
           <span className="hint">Enter 复制</span>
+          <span className="hint">Shift+Enter 逐字输入</span>
           <span className="hint">Delete 删除</span>
           <span className="hint">Esc 关闭</span>
           <button className="settings-btn" onClick={() => setShowSettings(true)}>⚙️</button>
         </div>
       </div>
 
+      {locked && (
+        <div className="settings-overlay">
+          <div className="settings-panel unlock-panel">
+            <div className="settings-header">
+              <h2>历史记录已加密</h2>
+            </div>
+            <div className="settings-content">
+              <div className="setting-item">
+                <label>输入口令解锁</label>
+                <input
+                  type="password"
+                  autoFocus
+                  value={passphrase}
+                  onChange={e => setPassphrase(e.target.value)}
+                  onKeyDown={e => {
+                    // 不触发全局的卡片快捷键
+                    e.stopPropagation();
+                    if (e.key === 'Enter') handleUnlock();
+                  }}
+                />
+                {unlockError && <span className="setting-hint error">{unlockError}</span>}
+              </div>
+            </div>
+            <div className="settings-footer">
+              <button className="btn-primary" onClick={handleUnlock}>解锁</button>
+            </div>
+          </div>
+        </div>
+      )}
+
       {showSettings && (
         <SettingsPanel
           settings={settings}
+          shortcutStatus={shortcutStatus}
           onUpdateShortcut={updateShortcut}
           onUpdateSettings={updateSettings}
           onClose={() => setShowSettings(false)}
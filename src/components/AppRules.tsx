@@ -0,0 +1,137 @@
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import type { AppRule, RuleAction } from '../types';
+
+type MatchField = 'app_id' | 'window_class' | 'title_pattern';
+
+const MATCH_LABELS: Record<MatchField, string> = {
+  app_id: '应用',
+  window_class: '窗口类名',
+  title_pattern: '标题正则',
+};
+
+const describeAction = (action: RuleAction) => {
+  switch (action.kind) {
+    case 'ignore':
+      return '不记录';
+    case 'keep_minutes':
+      return `保留 ${action.minutes} 分钟`;
+    case 'text_only':
+      return '只记录文本';
+  }
+};
+
+const describeMatch = (rule: AppRule) =>
+  (Object.keys(MATCH_LABELS) as MatchField[])
+    .filter(field => rule[field])
+    .map(field => `${MATCH_LABELS[field]}: ${rule[field]}`)
+    .join('，');
+
+// 规则保存后立即生效，不经过设置面板的保存按钮
+function AppRules() {
+  const [rules, setRules] = useState<AppRule[]>([]);
+  const [field, setField] = useState<MatchField>('app_id');
+  const [value, setValue] = useState('');
+  const [kind, setKind] = useState<RuleAction['kind']>('ignore');
+  const [minutes, setMinutes] = useState(10);
+  const [error, setError] = useState('');
+
+  const loadRules = async () => {
+    try {
+      setRules(await invoke<AppRule[]>('list_app_rules'));
+    } catch (e) {
+      console.error('Failed to load app rules:', e);
+    }
+  };
+
+  useEffect(() => {
+    loadRules();
+  }, []);
+
+  const saveRule = async (rule: AppRule) => {
+    setError('');
+    try {
+      await invoke('save_app_rule', { rule });
+      await loadRules();
+      return true;
+    } catch (e) {
+      setError(String(e));
+      return false;
+    }
+  };
+
+  const handleAdd = async () => {
+    const action: RuleAction = kind === 'keep_minutes' ? { kind, minutes } : { kind };
+    const rule: AppRule = {
+      id: null,
+      app_id: null,
+      window_class: null,
+      title_pattern: null,
+      action,
+      enabled: true,
+    };
+    rule[field] = value.trim() || null;
+    if (await saveRule(rule)) setValue('');
+  };
+
+  const handleDelete = async (id: number | null) => {
+    if (id === null) return;
+    try {
+      await invoke('delete_app_rule', { id });
+      await loadRules();
+    } catch (e) {
+      setError(String(e));
+    }
+  };
+
+  return (
+    <div className="setting-item app-rules">
+      <label>应用规则</label>
+      <ul>
+        {rules.map(rule => (
+          <li key={rule.id} className={rule.enabled ? '' : 'disabled'}>
+            <input
+              type="checkbox"
+              checked={rule.enabled}
+              onChange={e => saveRule({ ...rule, enabled: e.target.checked })}
+            />
+            <span className="rule-match">{describeMatch(rule)}</span>
+            <span className="rule-action">{describeAction(rule.action)}</span>
+            <button onClick={() => handleDelete(rule.id)} title="删除规则">✕</button>
+          </li>
+        ))}
+      </ul>
+      <div className="rule-form">
+        <select value={field} onChange={e => setField(e.target.value as MatchField)}>
+          {(Object.keys(MATCH_LABELS) as MatchField[]).map(f => (
+            <option key={f} value={f}>{MATCH_LABELS[f]}</option>
+          ))}
+        </select>
+        <input
+          type="text"
+          placeholder={field === 'title_pattern' ? '如 (?i)terminal' : '如 keepassxc'}
+          value={value}
+          onChange={e => setValue(e.target.value)}
+        />
+        <select value={kind} onChange={e => setKind(e.target.value as RuleAction['kind'])}>
+          <option value="ignore">不记录</option>
+          <option value="keep_minutes">限时保留</option>
+          <option value="text_only">只记录文本</option>
+        </select>
+        {kind === 'keep_minutes' && (
+          <input
+            type="number"
+            min={1}
+            value={minutes}
+            onChange={e => setMinutes(parseInt(e.target.value) || 10)}
+          />
+        )}
+        <button className="btn-secondary" onClick={handleAdd}>添加</button>
+      </div>
+      <span className="setting-hint">Wayland 下无法获知复制内容的来源应用，规则不生效</span>
+      {error && <span className="setting-hint error">{error}</span>}
+    </div>
+  );
+}
+
+export default AppRules;
@@ -1,15 +1,17 @@
 import { forwardRef } from 'react';
-import type { ClipboardItem } from '../types';
+import type { ClipboardItem, SourceApp } from '../types';
 import { convertFileSrc } from '@tauri-apps/api/core';
 
 interface Props {
   item: ClipboardItem;
   isSelected: boolean;
   onClick: () => void;
+  onFilterApp: (app: SourceApp) => void;
+  onTogglePin: () => void;
 }
 
 const ClipboardCard = forwardRef<HTMLDivElement, Props>(
-  ({ item, isSelected, onClick }, ref) => {
+  ({ item, isSelected, onClick, onFilterApp, onTogglePin }, ref) => {
     const formatTime = (dateStr: string) => {
       const date = new Date(dateStr + 'Z');
       const now = new Date();
@@ -23,19 +25,47 @@ const ClipboardCard = forwardRef<HTMLDivElement, Props>(
 
     const renderContent = () => {
       if (item.content_type === 'image') {
+        if (item.pending) {
+          return <div className="card-image pending">处理中…</div>;
+        }
         const src = item.thumbnail_path || item.image_path;
         return (
           <div className="card-image">
             <img 
-              src={src ? convertFileSrc(src) : ''} 
+              src={src ? convertFileSrc(src, 'sp-image') : ''} 
               alt="clipboard image" 
             />
+            {item.missing && <span className="card-missing">原图已丢失</span>}
+          </div>
+        );
+      }
+
+      if (item.content_type === 'files') {
+        return (
+          <div className="card-files">
+            {item.thumbnail_path && (
+              <img src={convertFileSrc(item.thumbnail_path, 'sp-image')} alt="file preview" />
+            )}
+            <ul>
+              {item.files.slice(0, 5).map(file => (
+                <li
+                  key={file.path}
+                  className={file.exists ? '' : 'missing'}
+                  title={file.path}
+                >
+                  {file.is_dir ? '📂' : '📄'} {file.path.split(/[\\/]/).pop()}
+                </li>
+              ))}
+              {item.files.length > 5 && (
+                <li className="more">等 {item.files.length} 个文件</li>
+              )}
+            </ul>
           </div>
         );
       }
 
       return (
-        <div className="card-text">
+        <div className={`card-text ${item.sensitive ? 'sensitive' : ''}`}>
           {item.text_content?.slice(0, 200)}
           {(item.text_content?.length || 0) > 200 && '...'}
         </div>
@@ -45,15 +75,40 @@ const ClipboardCard = forwardRef<HTMLDivElement, Props>(
     return (
       <div 
         ref={ref}
-        className={`card ${isSelected ? 'selected' : ''}`}
+        className={`card ${isSelected ? 'selected' : ''} ${item.pinned ? 'pinned' : ''}`}
         onClick={onClick}
       >
         <div className="card-content">
           {renderContent()}
         </div>
         <div className="card-footer">
-          <span className="card-type">
-            {item.content_type === 'image' ? '🖼️' : '📝'}
+          <span className="card-type" title={item.formats.join('\n') || undefined}>
+            {{ image: '🖼️', files: '📁', text: '📝' }[item.content_type]}
+            {item.formats.length > 0 && ' 富文本'}
+            {item.sensitive && <span title={item.sensitive}> 🔒</span>}
+          </span>
+          {item.source_app?.id && (
+            <span
+              className="card-app"
+              title={item.source_app.title ?? undefined}
+              onClick={(e) => {
+                // 点击来源应用只筛选，不触发粘贴
+                e.stopPropagation();
+                onFilterApp(item.source_app!);
+              }}
+            >
+              {item.source_app.name ?? item.source_app.id}
+            </span>
+          )}
+          <span
+            className="card-pin"
+            title={item.pinned ? '取消固定' : '固定，空间不足时不清理'}
+            onClick={(e) => {
+              e.stopPropagation();
+              onTogglePin();
+            }}
+          >
+            {item.pinned ? '📌' : '📍'}
           </span>
           <span className="card-time">{formatTime(item.updated_at)}</span>
         </div>
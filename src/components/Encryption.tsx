@@ -0,0 +1,86 @@
+import { useState, useEffect } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import type { EncryptionStatus } from '../types';
+
+type Mode = 'passphrase' | 'key_file';
+
+// 开启或关闭后立即生效，不经过设置面板的保存按钮
+function Encryption() {
+  const [status, setStatus] = useState<EncryptionStatus | null>(null);
+  const [mode, setMode] = useState<Mode>('passphrase');
+  const [passphrase, setPassphrase] = useState('');
+  const [busy, setBusy] = useState(false);
+  const [error, setError] = useState('');
+
+  useEffect(() => {
+    invoke<EncryptionStatus>('get_encryption_status')
+      .then(setStatus)
+      .catch(e => console.error('Failed to load encryption status:', e));
+  }, []);
+
+  const run = async (command: string, args: Record<string, unknown> = {}) => {
+    setBusy(true);
+    setError('');
+    try {
+      setStatus(await invoke<EncryptionStatus>(command, args));
+      setPassphrase('');
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(false);
+    }
+  };
+
+  const handleEnable = () =>
+    run('enable_encryption', { passphrase: mode === 'passphrase' ? passphrase : null });
+
+  if (!status) return null;
+
+  return (
+    <div className="setting-item encryption">
+      <label>加密历史记录</label>
+      {status.enabled ? (
+        <div className="encryption-row">
+          <span className="setting-hint">
+            已开启（{status.mode === 'passphrase' ? '口令' : '密钥文件'}）
+          </span>
+          {status.incomplete && (
+            <button className="btn-secondary" onClick={() => run('enable_encryption')} disabled={busy}>
+              {busy ? '加密中...' : '继续加密'}
+            </button>
+          )}
+          <button className="btn-secondary" onClick={() => run('disable_encryption')} disabled={busy}>
+            关闭
+          </button>
+        </div>
+      ) : (
+        <div className="encryption-row">
+          <select value={mode} onChange={e => setMode(e.target.value as Mode)}>
+            <option value="passphrase">口令</option>
+            <option value="key_file">密钥文件</option>
+          </select>
+          {mode === 'passphrase' && (
+            <input
+              type="password"
+              placeholder="至少 8 个字符"
+              value={passphrase}
+              onChange={e => setPassphrase(e.target.value)}
+            />
+          )}
+          <button className="btn-secondary" onClick={handleEnable} disabled={busy}>
+            {busy ? '加密中...' : '开启'}
+          </button>
+        </div>
+      )}
+      {status.incomplete && (
+        <span className="setting-hint error">上次加密未完成，部分已有记录仍是明文</span>
+      )}
+      {status.enabled && status.mode === 'passphrase' && (
+        <span className="setting-hint">每次启动后需输入口令才能查看历史</span>
+      )}
+      {error && <span className="setting-hint error">{error}</span>}
+    </div>
+  );
+}
+
+export default Encryption;
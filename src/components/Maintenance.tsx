@@ -0,0 +1,47 @@
+import { useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import type { MaintenanceReport } from '../types';
+
+const describeReport = (report: MaintenanceReport) => {
+  const parts = [
+    `删除 ${report.orphans_removed} 个孤立文件（${(report.freed_bytes / 1024 / 1024).toFixed(1)} MB）`,
+    `重建 ${report.thumbnails_regenerated} 张缩略图`,
+  ];
+  if (report.thumbnails_dropped > 0) parts.push(`移除 ${report.thumbnails_dropped} 张无法重建的缩略图`);
+  if (report.missing_originals > 0) parts.push(`${report.missing_originals} 张图片原图已丢失`);
+  if (report.corrupt_originals > 0) parts.push(`${report.corrupt_originals} 张图片原图已损坏`);
+  return parts.join('，');
+};
+
+// 对照历史记录清理 images 目录并修复缩略图
+function Maintenance() {
+  const [running, setRunning] = useState(false);
+  const [result, setResult] = useState('');
+  const [error, setError] = useState('');
+
+  const handleRun = async () => {
+    setRunning(true);
+    setResult('');
+    setError('');
+    try {
+      setResult(describeReport(await invoke<MaintenanceReport>('run_maintenance')));
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  return (
+    <div className="setting-item">
+      <label>图片文件</label>
+      <button className="btn-secondary" onClick={handleRun} disabled={running}>
+        {running ? '检查中...' : '清理与修复'}
+      </button>
+      {result && <span className="setting-hint">{result}</span>}
+      {error && <span className="setting-hint error">{error}</span>}
+    </div>
+  );
+}
+
+export default Maintenance;
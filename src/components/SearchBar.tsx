@@ -1,8 +1,11 @@
 import { useRef, useImperativeHandle, forwardRef } from 'react';
+import type { SourceApp } from '../types';
 
 interface Props {
   value: string;
   onChange: (value: string) => void;
+  appFilter: SourceApp | null;
+  onClearAppFilter: () => void;
 }
 
 export interface SearchBarRef {
@@ -11,7 +14,7 @@ export interface SearchBarRef {
   isFocused: () => boolean;
 }
 
-const SearchBar = forwardRef<SearchBarRef, Props>(({ value, onChange }, ref) => {
+const SearchBar = forwardRef<SearchBarRef, Props>(({ value, onChange, appFilter, onClearAppFilter }, ref) => {
   const inputRef = useRef<HTMLInputElement>(null);
 
   useImperativeHandle(ref, () => ({
@@ -23,6 +26,11 @@ const SearchBar = forwardRef<SearchBarRef, Props>(({ value, onChange }, ref) =>
   return (
     <div className="search-bar">
       <span className="search-icon">🔍</span>
+      {appFilter && (
+        <button className="app-filter" onClick={onClearAppFilter} title="取消应用筛选">
+          {appFilter.name ?? appFilter.id} ×
+        </button>
+      )}
       <input
         ref={inputRef}
         type="text"
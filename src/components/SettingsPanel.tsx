@@ -1,10 +1,14 @@
 import { useState } from 'react';
-import type { Settings } from '../types';
+import type { PasteChord, PasteChordRule, SecretPattern, Settings, SettingsError, ShortcutStatus } from '../types';
+import AppRules from './AppRules';
+import Encryption from './Encryption';
+import Maintenance from './Maintenance';
 
 interface Props {
   settings: Settings;
-  onUpdateShortcut: (shortcut: string) => Promise<boolean>;
-  onUpdateSettings: (updates: Partial<Settings>) => Promise<boolean>;
+  shortcutStatus: ShortcutStatus | null;
+  onUpdateShortcut: (shortcut: string) => Promise<SettingsError | null>;
+  onUpdateSettings: (updates: Partial<Settings>) => Promise<SettingsError | null>;
   onClose: () => void;
 }
 
@@ -16,10 +20,66 @@ const SHORTCUT_OPTIONS = [
   'CommandOrControl+Shift+P',
 ];
 
-function SettingsPanel({ settings, onUpdateShortcut, onUpdateSettings, onClose }: Props) {
+// 密钥规则在文本框中每行一条，格式为「名称=正则」
+const formatPatterns = (patterns: SecretPattern[]) =>
+  patterns.map(p => `${p.name}=${p.pattern}`).join('\n');
+
+const parsePatterns = (text: string): SecretPattern[] =>
+  text
+    .split('\n')
+    .map(line => line.trim())
+    .filter(line => line.includes('='))
+    .map(line => {
+      const index = line.indexOf('=');
+      return { name: line.slice(0, index).trim(), pattern: line.slice(index + 1).trim() };
+    });
+
+const PASTE_CHORDS: PasteChord[] = ['ctrl_v', 'ctrl_shift_v', 'shift_insert', 'middle_click', 'type_text'];
+
+// 粘贴方式在文本框中每行一条，格式为「应用=方式」，方式不认识的行忽略
+const formatChords = (rules: PasteChordRule[]) =>
+  rules.map(r => `${r.app}=${r.chord}`).join('\n');
+
+const parseChords = (text: string): PasteChordRule[] =>
+  text
+    .split('\n')
+    .map(line => line.trim())
+    .filter(line => line.includes('='))
+    .map(line => {
+      const index = line.lastIndexOf('=');
+      return { app: line.slice(0, index).trim(), chord: line.slice(index + 1).trim() as PasteChord };
+    })
+    .filter(rule => PASTE_CHORDS.includes(rule.chord));
+
+const describeError = (error: SettingsError) => {
+  switch (error.kind) {
+    case 'shortcut':
+      return error.error.kind === 'conflict'
+        ? '快捷键设置失败：已被其他程序占用，请尝试其他组合'
+        : '快捷键设置失败：格式无效';
+    case 'invalid':
+      return `保存失败：${error.message}`;
+    default:
+      return '保存失败';
+  }
+};
+
+function SettingsPanel({ settings, shortcutStatus, onUpdateShortcut, onUpdateSettings, onClose }: Props) {
   const [shortcut, setShortcut] = useState(settings.shortcut);
   const [maxItems, setMaxItems] = useState(settings.max_items);
   const [maxDays, setMaxDays] = useState(settings.max_days);
+  const [maxFileSizeMb, setMaxFileSizeMb] = useState(settings.max_file_size_mb);
+  const [maxImageStorageMb, setMaxImageStorageMb] = useState(settings.max_image_storage_mb);
+  const [mergeSimilar, setMergeSimilar] = useState(settings.merge_similar_images);
+  const [similarDistance, setSimilarDistance] = useState(settings.similar_image_distance);
+  const [moveToTop, setMoveToTop] = useState(settings.move_to_top_on_paste);
+  const [restoreClipboard, setRestoreClipboard] = useState(settings.restore_clipboard_after_paste);
+  const [detectSecrets, setDetectSecrets] = useState(settings.detect_secrets);
+  const [secretTtl, setSecretTtl] = useState(settings.secret_ttl_minutes);
+  const [secretPatterns, setSecretPatterns] = useState(formatPatterns(settings.secret_patterns));
+  const [pasteChords, setPasteChords] = useState(formatChords(settings.paste_chords));
+  const [typeDelay, setTypeDelay] = useState(settings.type_delay_ms);
+  const [typeAbortShortcut, setTypeAbortShortcut] = useState(settings.type_abort_shortcut);
   const [saving, setSaving] = useState(false);
   const [message, setMessage] = useState('');
 
@@ -29,18 +89,33 @@ function SettingsPanel({ settings, onUpdateShortcut, onUpdateSettings, onClose }
 
     try {
       if (shortcut !== settings.shortcut) {
-        const success = await onUpdateShortcut(shortcut);
-        if (!success) {
-          setMessage('快捷键设置失败，请尝试其他组合');
-          setSaving(false);
+        const error = await onUpdateShortcut(shortcut);
+        if (error) {
+          setMessage(describeError(error));
           return;
         }
       }
 
-      await onUpdateSettings({
+      const error = await onUpdateSettings({
         max_items: maxItems,
         max_days: maxDays,
+        max_file_size_mb: maxFileSizeMb,
+        max_image_storage_mb: maxImageStorageMb,
+        merge_similar_images: mergeSimilar,
+        similar_image_distance: similarDistance,
+        move_to_top_on_paste: moveToTop,
+        restore_clipboard_after_paste: restoreClipboard,
+        detect_secrets: detectSecrets,
+        secret_ttl_minutes: secretTtl,
+        secret_patterns: parsePatterns(secretPatterns),
+        paste_chords: parseChords(pasteChords),
+        type_delay_ms: typeDelay,
+        type_abort_shortcut: typeAbortShortcut.trim(),
       });
+      if (error) {
+        setMessage(describeError(error));
+        return;
+      }
 
       setMessage('设置已保存');
       setTimeout(() => onClose(), 1000);
@@ -80,6 +155,13 @@ function SettingsPanel({ settings, onUpdateShortcut, onUpdateSettings, onClose }
                 </option>
               ))}
             </select>
+            {shortcutStatus?.error && (
+              <span className="setting-hint error">
+                {shortcutStatus.error.kind === 'conflict'
+                  ? `${formatShortcut(shortcutStatus.error.accelerator)} 已被占用，快捷键未生效`
+                  : '快捷键格式无效，快捷键未生效'}
+              </span>
+            )}
           </div>
 
           <div className="setting-item">
@@ -103,6 +185,154 @@ function SettingsPanel({ settings, onUpdateShortcut, onUpdateSettings, onClose }
               onChange={e => setMaxDays(parseInt(e.target.value) || 30)}
             />
           </div>
+
+          <div className="setting-item">
+            <label>单条大小上限 (MB)</label>
+            <input
+              type="number"
+              min={1}
+              max={1024}
+              value={maxFileSizeMb}
+              onChange={e => setMaxFileSizeMb(parseInt(e.target.value) || 10)}
+            />
+          </div>
+
+          <div className="setting-item">
+            <label>图片总空间上限 (MB)</label>
+            <input
+              type="number"
+              min={50}
+              max={102400}
+              value={maxImageStorageMb}
+              onChange={e => setMaxImageStorageMb(parseInt(e.target.value) || 1024)}
+            />
+            <span className="setting-hint">超出时自动删除最旧的未固定图片</span>
+          </div>
+
+          <div className="setting-item checkbox">
+            <label>
+              <input
+                type="checkbox"
+                checked={mergeSimilar}
+                onChange={e => setMergeSimilar(e.target.checked)}
+              />
+              合并看起来相同的图片（如重复截图）
+            </label>
+          </div>
+
+          {mergeSimilar && (
+            <div className="setting-item">
+              <label>相似图片阈值</label>
+              <input
+                type="number"
+                min={0}
+                max={16}
+                value={similarDistance}
+                onChange={e => setSimilarDistance(parseInt(e.target.value) || 0)}
+              />
+              <span className="setting-hint">数值越大越容易合并，0 表示只合并几乎完全相同的图片</span>
+            </div>
+          )}
+
+          <div className="setting-item checkbox">
+            <label>
+              <input
+                type="checkbox"
+                checked={moveToTop}
+                onChange={e => setMoveToTop(e.target.checked)}
+              />
+              粘贴历史条目时移到最前
+            </label>
+          </div>
+
+          <div className="setting-item checkbox">
+            <label>
+              <input
+                type="checkbox"
+                checked={restoreClipboard}
+                onChange={e => setRestoreClipboard(e.target.checked)}
+              />
+              粘贴后恢复原来的剪贴板内容
+            </label>
+          </div>
+
+          <div className="setting-item checkbox">
+            <label>
+              <input
+                type="checkbox"
+                checked={detectSecrets}
+                onChange={e => setDetectSecrets(e.target.checked)}
+              />
+              识别密钥、令牌和卡号，列表中遮盖并自动删除
+            </label>
+          </div>
+
+          {detectSecrets && (
+            <>
+              <div className="setting-item">
+                <label>密钥保留时间 (分钟)</label>
+                <input
+                  type="number"
+                  min={1}
+                  max={1440}
+                  value={secretTtl}
+                  onChange={e => setSecretTtl(parseInt(e.target.value) || 5)}
+                />
+              </div>
+
+              <div className="setting-item">
+                <label>密钥规则（每行「名称=正则」）</label>
+                <textarea
+                  rows={5}
+                  spellCheck={false}
+                  value={secretPatterns}
+                  onChange={e => setSecretPatterns(e.target.value)}
+                />
+              </div>
+            </>
+          )}
+
+          <div className="setting-item">
+            <label>粘贴方式（每行「应用=方式」）</label>
+            <textarea
+              rows={5}
+              spellCheck={false}
+              value={pasteChords}
+              onChange={e => setPasteChords(e.target.value)}
+            />
+            <span className="setting-hint">
+              应用填写窗口类名或进程名，方式为 {PASTE_CHORDS.join(' / ')}，未列出的应用使用 Ctrl+V。
+              Wayland 下无法获知目标应用，始终使用 Ctrl+V
+            </span>
+          </div>
+
+          <div className="setting-item">
+            <label>逐字输入间隔 (毫秒)</label>
+            <input
+              type="number"
+              min={0}
+              max={1000}
+              value={typeDelay}
+              onChange={e => setTypeDelay(parseInt(e.target.value) || 0)}
+            />
+            <span className="setting-hint">Shift+Enter 或 type_text 方式把文本模拟为按键逐字输入</span>
+          </div>
+
+          <div className="setting-item">
+            <label>中止逐字输入的快捷键</label>
+            <input
+              type="text"
+              spellCheck={false}
+              value={typeAbortShortcut}
+              onChange={e => setTypeAbortShortcut(e.target.value)}
+            />
+          </div>
+
+          <AppRules />
+
+          <Encryption />
+
+          <Maintenance />
         </div>
 
         {message && (
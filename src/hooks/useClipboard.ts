@@ -1,169 +1,98 @@
-import { useEffect, useState, useCallback } from 'react';
+import { useEffect, useState, useCallback, useRef } from 'react';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
-import Database from '@tauri-apps/plugin-sql';
-import type { ClipboardItem } from '../types';
-
-const DB_NAME = 'sqlite:super-paste.db';
-
-interface ClipboardPayload {
-  content_type: string;
-  content_hash: string;
-  text_content: string | null;
-  image_path: string | null;
-  thumbnail_path: string | null;
-  file_size: number;
-}
+import type { ClipboardItem, ImageReady, SourceApp } from '../types';
 
 export function useClipboard() {
   const [items, setItems] = useState<ClipboardItem[]>([]);
-  const [db, setDb] = useState<Awaited<ReturnType<typeof Database.load>> | null>(null);
+  const [appFilter, setAppFilter] = useState<SourceApp | null>(null);
+  // 当前搜索词和来源应用，新内容到达时按它们刷新列表
+  const queryRef = useRef('');
+  const appRef = useRef<string | null>(null);
+  // 文件仍在后台写入的图片，数据库里查不到这个状态
+  const pendingRef = useRef(new Set<string>());
+
+  const loadItems = useCallback(async () => {
+    const query = queryRef.current;
+    const app = appRef.current;
+    const result = query.trim()
+      ? await invoke<ClipboardItem[]>('search_items', { query, app })
+      : await invoke<ClipboardItem[]>('list_items', { app });
+    setItems(result.map(item =>
+      pendingRef.current.has(item.content_hash) ? { ...item, pending: true } : item
+    ));
+  }, []);
 
-  // Initialize database
+  // Initial load
   useEffect(() => {
-    async function initDb() {
-      const database = await Database.load(DB_NAME);
-      
-      await database.execute(`
-        CREATE TABLE IF NOT EXISTS clipboard_items (
-          id INTEGER PRIMARY KEY AUTOINCREMENT,
-          content_type TEXT NOT NULL,
-          content_hash TEXT NOT NULL UNIQUE,
-          text_content TEXT,
-          image_path TEXT,
-          thumbnail_path TEXT,
-          file_size INTEGER DEFAULT 0,
-          source_app TEXT,
-          created_at TEXT DEFAULT (datetime('now')),
-          updated_at TEXT DEFAULT (datetime('now'))
-        )
-      `);
-
-      await database.execute(`
-        CREATE INDEX IF NOT EXISTS idx_updated_at ON clipboard_items(updated_at DESC)
-      `);
+    loadItems();
+  }, [loadItems]);
 
-      setDb(database);
-      await loadItems(database);
-    }
+  // Listen for clipboard changes (already persisted by the backend)
+  useEffect(() => {
+    const unlisten = listen<ClipboardItem>('clipboard-changed', async (event) => {
+      if (event.payload.pending) pendingRef.current.add(event.payload.content_hash);
+      await loadItems();
+    });
 
-    initDb();
-  }, []);
+    // 图片文件写完后显示缩略图，写入失败的条目已被后端删除
+    const unlistenImage = listen<ImageReady>('image-ready', async (event) => {
+      pendingRef.current.delete(event.payload.content_hash);
+      await loadItems();
+    });
 
-  const loadItems = async (database: Awaited<ReturnType<typeof Database.load>>) => {
-    const result = await database.select<ClipboardItem[]>(
-      'SELECT * FROM clipboard_items ORDER BY updated_at DESC LIMIT 100'
-    );
-    setItems(result);
-  };
+    // 保留策略变化时后端会立即清理旧记录
+    const unlistenSettings = listen('settings-changed', async () => {
+      await loadItems();
+    });
 
-  // Listen for clipboard changes
-  useEffect(() => {
-    if (!db) return;
+    // 后台定期清理删除了过期或超出限制的记录
+    const unlistenPruned = listen('history-pruned', async () => {
+      await loadItems();
+    });
 
-    const unlistenText = listen<ClipboardPayload>('clipboard-text', async (event) => {
-      await saveItem(event.payload);
+    // 清理与修复后缩略图和原图状态可能变化
+    const unlistenRepaired = listen('history-repaired', async () => {
+      await loadItems();
     });
 
-    const unlistenImage = listen<ClipboardPayload>('clipboard-image', async (event) => {
-      await saveItem(event.payload);
+    // 解锁后才能读取加密的历史
+    const unlistenUnlock = listen('history-unlocked', async () => {
+      await loadItems();
     });
 
     return () => {
-      unlistenText.then(fn => fn());
+      unlisten.then(fn => fn());
       unlistenImage.then(fn => fn());
+      unlistenSettings.then(fn => fn());
+      unlistenPruned.then(fn => fn());
+      unlistenRepaired.then(fn => fn());
+      unlistenUnlock.then(fn => fn());
     };
-  }, [db]);
-
-  const saveItem = async (payload: ClipboardPayload) => {
-    if (!db) return;
-
-    // Check if exists (dedup by hash)
-    const existing = await db.select<ClipboardItem[]>(
-      'SELECT id FROM clipboard_items WHERE content_hash = ?',
-      [payload.content_hash]
-    );
-
-    if (existing.length > 0) {
-      // Update timestamp only
-      await db.execute(
-        "UPDATE clipboard_items SET updated_at = datetime('now') WHERE content_hash = ?",
-        [payload.content_hash]
-      );
-    } else {
-      // Insert new
-      await db.execute(
-        `INSERT INTO clipboard_items (content_type, content_hash, text_content, image_path, thumbnail_path, file_size)
-         VALUES (?, ?, ?, ?, ?, ?)`,
-        [
-          payload.content_type,
-          payload.content_hash,
-          payload.text_content,
-          payload.image_path,
-          payload.thumbnail_path,
-          payload.file_size,
-        ]
-      );
-    }
-
-    // Cleanup old items (keep max 1000)
-    await db.execute(`
-      DELETE FROM clipboard_items WHERE id NOT IN (
-        SELECT id FROM clipboard_items ORDER BY updated_at DESC LIMIT 1000
-      )
-    `);
-
-    // Cleanup items older than 30 days
-    await db.execute(`
-      DELETE FROM clipboard_items WHERE updated_at < datetime('now', '-30 days')
-    `);
-
-    await loadItems(db);
-  };
-
-  const updateItemTime = useCallback(async (hash: string) => {
-    if (!db) return;
-    await db.execute(
-      "UPDATE clipboard_items SET updated_at = datetime('now') WHERE content_hash = ?",
-      [hash]
-    );
-    await loadItems(db);
-  }, [db]);
+  }, [loadItems]);
 
   const searchItems = useCallback(async (query: string) => {
-    if (!db) return;
-    if (!query.trim()) {
-      await loadItems(db);
-      return;
-    }
-    const result = await db.select<ClipboardItem[]>(
-      `SELECT * FROM clipboard_items 
-       WHERE text_content LIKE ? 
-       ORDER BY updated_at DESC LIMIT 100`,
-      [`%${query}%`]
-    );
-    setItems(result);
-  }, [db]);
+    queryRef.current = query;
+    await loadItems();
+  }, [loadItems]);
+
+  // 只显示某个应用复制的内容，传 null 取消筛选
+  const filterByApp = useCallback(async (app: SourceApp | null) => {
+    appRef.current = app?.id ?? null;
+    setAppFilter(app);
+    await loadItems();
+  }, [loadItems]);
 
   const deleteItem = useCallback(async (item: ClipboardItem) => {
-    if (!db) return;
-    
-    // 删除图片文件（如果存在）
-    if (item.image_path || item.thumbnail_path) {
-      try {
-        await invoke('delete_image_files', {
-          imagePath: item.image_path,
-          thumbnailPath: item.thumbnail_path,
-        });
-      } catch (e) {
-        console.error('Failed to delete image files:', e);
-      }
-    }
-    
-    // 从数据库删除记录
-    await db.execute('DELETE FROM clipboard_items WHERE id = ?', [item.id]);
-    await loadItems(db);
-  }, [db]);
+    // 后端同时删除记录和图片文件
+    await invoke('delete_item', { id: item.id });
+    await loadItems();
+  }, [loadItems]);
+
+  const togglePin = useCallback(async (item: ClipboardItem) => {
+    await invoke('set_pinned', { id: item.id, pinned: !item.pinned });
+    await loadItems();
+  }, [loadItems]);
 
-  return { items, updateItemTime, searchItems, deleteItem };
+  return { items, appFilter, searchItems, filterByApp, deleteItem, togglePin };
 }
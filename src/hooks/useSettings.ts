@@ -1,50 +1,58 @@
-import { useState, useEffect, useCallback } from 'react';
+import { useState, useEffect, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { register, unregister } from '@tauri-apps/plugin-global-shortcut';
-import Database from '@tauri-apps/plugin-sql';
-import type { Settings } from '../types';
+import { listen } from '@tauri-apps/api/event';
+import type { Settings, SettingsError, ShortcutStatus } from '../types';
 
-const DB_NAME = 'sqlite:super-paste.db';
 const DEFAULT_SETTINGS: Settings = {
   shortcut: 'CommandOrControl+Shift+V',
   max_items: 1000,
   max_days: 30,
   max_file_size_mb: 10,
+  move_to_top_on_paste: true,
+  detect_secrets: true,
+  secret_ttl_minutes: 5,
+  secret_patterns: [],
+  max_image_storage_mb: 1024,
+  merge_similar_images: false,
+  similar_image_distance: 4,
+  paste_chords: [],
+  type_delay_ms: 10,
+  type_abort_shortcut: 'Escape',
+  restore_clipboard_after_paste: false,
 };
 
 export function useSettings() {
   const [settings, setSettings] = useState<Settings>(DEFAULT_SETTINGS);
+  const [shortcutStatus, setShortcutStatus] = useState<ShortcutStatus | null>(null);
   const [loading, setLoading] = useState(true);
+  // 连续保存时基于最新值合并，避免闭包中的旧设置覆盖刚保存的字段
+  const settingsRef = useRef(settings);
+  settingsRef.current = settings;
 
   useEffect(() => {
     loadSettings();
   }, []);
 
-  const loadSettings = async () => {
-    try {
-      const db = await Database.load(DB_NAME);
-      
-      await db.execute(`
-        CREATE TABLE IF NOT EXISTS settings (
-          key TEXT PRIMARY KEY,
-          value TEXT NOT NULL
-        )
-      `);
+  // 后端保存成功后广播最新设置
+  useEffect(() => {
+    const unlisten = listen<Settings>('settings-changed', (event) => {
+      setSettings(event.payload);
+    });
+    return () => { unlisten.then(fn => fn()); };
+  }, []);
 
-      const rows = await db.select<{ key: string; value: string }[]>(
-        'SELECT key, value FROM settings'
-      );
+  const loadShortcutStatus = async () => {
+    try {
+      setShortcutStatus(await invoke<ShortcutStatus>('get_shortcut_status'));
+    } catch (e) {
+      console.error('Failed to load shortcut status:', e);
+    }
+  };
 
-      if (rows.length > 0) {
-        const loaded: Partial<Settings> = {};
-        for (const row of rows) {
-          if (row.key === 'shortcut') loaded.shortcut = row.value;
-          if (row.key === 'max_items') loaded.max_items = parseInt(row.value);
-          if (row.key === 'max_days') loaded.max_days = parseInt(row.value);
-          if (row.key === 'max_file_size_mb') loaded.max_file_size_mb = parseInt(row.value);
-        }
-        setSettings({ ...DEFAULT_SETTINGS, ...loaded });
-      }
+  const loadSettings = async () => {
+    try {
+      setSettings(await invoke<Settings>('get_settings'));
+      await loadShortcutStatus();
     } catch (e) {
       console.error('Failed to load settings:', e);
     } finally {
@@ -52,55 +60,32 @@ export function useSettings() {
     }
   };
 
-  const updateShortcut = useCallback(async (newShortcut: string) => {
+  // 返回 null 表示保存成功，否则返回后端给出的错误
+  const saveSettings = useCallback(async (next: Settings): Promise<SettingsError | null> => {
     try {
-      // Unregister old shortcut
-      await unregister(settings.shortcut);
-      
-      // Register new shortcut
-      await register(newShortcut, () => {
-        invoke('show_window');
-      });
-
-      // Save to database
-      const db = await Database.load(DB_NAME);
-      await db.execute(
-        `INSERT OR REPLACE INTO settings (key, value) VALUES ('shortcut', ?)`,
-        [newShortcut]
-      );
-
-      setSettings(prev => ({ ...prev, shortcut: newShortcut }));
-      return true;
+      await invoke('save_settings', { settings: next });
+      settingsRef.current = next;
+      setSettings(next);
+      return null;
     } catch (e) {
-      console.error('Failed to update shortcut:', e);
-      // Re-register old shortcut on failure
-      await register(settings.shortcut, () => {
-        invoke('show_window');
-      });
-      return false;
-    }
-  }, [settings.shortcut]);
-
-  const updateSettings = useCallback(async (updates: Partial<Settings>) => {
-    try {
-      const db = await Database.load(DB_NAME);
-      
-      for (const [key, value] of Object.entries(updates)) {
-        if (key !== 'shortcut') {
-          await db.execute(
-            `INSERT OR REPLACE INTO settings (key, value) VALUES (?, ?)`,
-            [key, String(value)]
-          );
-        }
+      console.error('Failed to save settings:', e);
+      if (typeof e === 'object' && e !== null && 'kind' in e) {
+        return e as SettingsError;
       }
-
-      setSettings(prev => ({ ...prev, ...updates }));
-      return true;
-    } catch (e) {
-      console.error('Failed to update settings:', e);
-      return false;
+      return { kind: 'storage', message: String(e) };
+    } finally {
+      await loadShortcutStatus();
     }
   }, []);
 
-  return { settings, loading, updateShortcut, updateSettings };
+  const updateShortcut = useCallback(async (newShortcut: string) => {
+    // 快捷键由后端注册，失败时原快捷键保持生效
+    return saveSettings({ ...settingsRef.current, shortcut: newShortcut });
+  }, [saveSettings]);
+
+  const updateSettings = useCallback(async (updates: Partial<Settings>) => {
+    return saveSettings({ ...settingsRef.current, ...updates });
+  }, [saveSettings]);
+
+  return { settings, shortcutStatus, loading, updateShortcut, updateSettings };
 }
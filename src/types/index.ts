@@ -1,14 +1,58 @@
 export interface ClipboardItem {
   id: number;
-  content_type: 'text' | 'image';
+  content_type: 'text' | 'image' | 'files';
   content_hash: string;
   text_content: string | null;
   image_path: string | null;
   thumbnail_path: string | null;
   file_size: number;
-  source_app: string | null;
+  source_app: SourceApp | null;
   created_at: string;
   updated_at: string;
+  formats: string[]; // 额外保存的 MIME 类型，如 text/html
+  files: FileEntry[]; // files 类型条目复制时的文件状态
+  expires_at: string | null; // 按规则限时保留的条目到期时间
+  sensitive: string | null; // 识别出的密钥类型，列表中文本已遮盖
+  pinned: boolean; // 固定的条目不会因图片空间超限被清理
+  missing: boolean; // 图片条目的原图文件已不存在
+  pending?: boolean; // 图片仍在后台写入
+}
+
+export interface SourceApp {
+  id: string | null;
+  name: string | null;
+  window_class: string | null;
+  title: string | null;
+  pid: number | null;
+}
+
+// 未记录的剪贴板内容，例如密码管理器标记为敏感的条目
+export interface SkippedCapture {
+  reason: string;
+  detail: string | null;
+  source_app: SourceApp | null;
+}
+
+export interface FileEntry {
+  path: string;
+  size: number;
+  exists: boolean;
+  is_dir: boolean;
+}
+
+export type RuleAction =
+  | { kind: 'ignore' }
+  | { kind: 'keep_minutes'; minutes: number }
+  | { kind: 'text_only' };
+
+// 按来源应用生效的捕获规则，填写的条件需全部满足
+export interface AppRule {
+  id: number | null;
+  app_id: string | null;
+  window_class: string | null;
+  title_pattern: string | null;
+  action: RuleAction;
+  enabled: boolean;
 }
 
 export interface Settings {
@@ -16,4 +60,74 @@ export interface Settings {
   max_items: number;
   max_days: number;
   max_file_size_mb: number;
+  move_to_top_on_paste: boolean;
+  detect_secrets: boolean;
+  secret_ttl_minutes: number;
+  secret_patterns: SecretPattern[];
+  max_image_storage_mb: number;
+  merge_similar_images: boolean;
+  similar_image_distance: number;
+  paste_chords: PasteChordRule[];
+  type_delay_ms: number;
+  type_abort_shortcut: string;
+  restore_clipboard_after_paste: boolean;
+}
+
+// 清理与修复的结果
+export interface MaintenanceReport {
+  orphans_removed: number;
+  freed_bytes: number;
+  thumbnails_regenerated: number;
+  thumbnails_dropped: number;
+  missing_originals: number;
+  corrupt_originals: number;
+}
+
+// 图片空间超限后被清理的条目
+export interface ImageEviction {
+  evicted: number[];
+  freed_bytes: number;
+  used_bytes: number;
+  quota_bytes: number;
+}
+
+// 后台写完图片文件，error 不为空时条目已被删除
+export interface ImageReady {
+  content_hash: string;
+  error: string | null;
+}
+
+// 历史记录静态加密状态，locked 表示使用口令且尚未解锁
+export interface EncryptionStatus {
+  enabled: boolean;
+  mode: 'passphrase' | 'key_file' | null;
+  locked: boolean;
+  incomplete: boolean;
+}
+
+export interface SecretPattern {
+  name: string;
+  pattern: string;
+}
+
+// 粘贴到目标应用时模拟的操作
+export type PasteChord = 'ctrl_v' | 'ctrl_shift_v' | 'shift_insert' | 'middle_click' | 'type_text';
+
+export interface PasteChordRule {
+  app: string;
+  chord: PasteChord;
+}
+
+export type ShortcutError =
+  | { kind: 'invalid'; accelerator: string; message: string }
+  | { kind: 'conflict'; accelerator: string; message: string };
+
+export type SettingsError =
+  | { kind: 'invalid'; message: string }
+  | { kind: 'storage'; message: string }
+  | { kind: 'shortcut'; error: ShortcutError };
+
+export interface ShortcutStatus {
+  accelerator: string | null;
+  error: ShortcutError | null;
 }